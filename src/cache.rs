@@ -0,0 +1,271 @@
+use std::{
+    num::NonZeroUsize,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use lru::LruCache;
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_config::RpcAccountInfoConfig,
+    rpc_request::RpcRequest,
+    rpc_response::{Response, RpcResponseContext},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use crate::config::CONFIG;
+
+lazy_static::lazy_static! {
+    static ref ACCOUNT_CACHE: AccountCache =
+        AccountCache::new(CONFIG.cache_capacity, Duration::from_secs(CONFIG.cache_ttl));
+}
+
+/// An account lookup, missing accounts included, with the slot it was read at
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedAccount {
+    pub slot: u64,
+    pub account: Option<Account>,
+}
+
+type Entry = (Instant, CachedAccount);
+
+/// Caches account lookups, including missing accounts, per RPC endpoint
+pub struct AccountCache {
+    entries: Mutex<LruCache<(String, Pubkey), Entry>>,
+    ttl: Duration,
+}
+
+impl AccountCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::MIN);
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            ttl,
+        }
+    }
+
+    pub fn get(&self, endpoint: &str, key: &Pubkey) -> Option<CachedAccount> {
+        if self.ttl.is_zero() {
+            return None;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let cache_key = (endpoint.to_owned(), *key);
+        match entries.get(&cache_key) {
+            Some((inserted_at, account)) if inserted_at.elapsed() < self.ttl => {
+                Some(account.clone())
+            }
+            Some(_) => {
+                entries.pop(&cache_key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, endpoint: &str, key: Pubkey, account: CachedAccount) {
+        if self.ttl.is_zero() {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.put((endpoint.to_owned(), key), (Instant::now(), account));
+    }
+}
+
+pub fn get_account_cache() -> &'static AccountCache {
+    &ACCOUNT_CACHE
+}
+
+/// Builds a client whose account reads are served from the cache when possible. It is used when
+/// no read config is requested, the reads of a request with a commitment or `minContextSlot` go
+/// through `sns::context` instead.
+pub fn get_cached_rpc_client(endpoint_url: String) -> RpcClient {
+    let sender = CachedSender {
+        inner: HttpSender::new(endpoint_url),
+    };
+    RpcClient::new_sender(sender, RpcClientConfig::default())
+}
+
+/// Transport answering `getAccountInfo` and `getMultipleAccounts` from the account cache, only
+/// the missing accounts are requested from the endpoint
+struct CachedSender {
+    inner: HttpSender,
+}
+
+#[async_trait]
+impl RpcSender for CachedSender {
+    async fn send(&self, request: RpcRequest, params: Value) -> ClientResult<Value> {
+        let Some((keys, config)) = parse_account_request(request, &params) else {
+            return self.inner.send(request, params).await;
+        };
+        let cache = get_account_cache();
+        let endpoint = self.inner.url();
+        let encoding = config.encoding.unwrap_or(UiAccountEncoding::Binary);
+        let mut result = keys
+            .iter()
+            .map(|k| {
+                cache.get(&endpoint, k).map(|cached| {
+                    let account = cached
+                        .account
+                        .map(|a| UiAccount::encode(k, &a, encoding, None, None));
+                    (cached.slot, account)
+                })
+            })
+            .collect::<Vec<_>>();
+        let missing = keys
+            .iter()
+            .zip(result.iter())
+            .filter(|(_, a)| a.is_none())
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let missing_params = json!([
+                missing.iter().map(Pubkey::to_string).collect::<Vec<_>>(),
+                config
+            ]);
+            let response = self
+                .inner
+                .send(RpcRequest::GetMultipleAccounts, missing_params)
+                .await?;
+            let response: Response<Vec<Option<UiAccount>>> = serde_json::from_value(response)?;
+            let slot = response.context.slot;
+            let mut fetched = missing.into_iter().zip(response.value);
+            for entry in result.iter_mut().filter(|a| a.is_none()) {
+                let Some((key, account)) = fetched.next() else {
+                    break;
+                };
+                // Accounts that cannot be decoded are returned as is without being cached
+                match account.as_ref().map(UiAccount::decode) {
+                    Some(None) => (),
+                    decoded => cache.insert(
+                        &endpoint,
+                        key,
+                        CachedAccount {
+                            slot,
+                            account: decoded.flatten(),
+                        },
+                    ),
+                }
+                *entry = Some((slot, account));
+            }
+        }
+        let entries = result
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| ClientErrorKind::Custom("Missing accounts in response".to_owned()))?;
+        // All the accounts reflect at least the lowest slot they were read at
+        let slot = entries.iter().map(|(s, _)| *s).min().unwrap_or_default();
+        let mut accounts = entries.into_iter().map(|(_, a)| a);
+        let context = RpcResponseContext {
+            slot,
+            api_version: None,
+        };
+        let response = match request {
+            RpcRequest::GetAccountInfo => serde_json::to_value(Response {
+                context,
+                value: accounts.next().flatten(),
+            }),
+            _ => serde_json::to_value(Response {
+                context,
+                value: accounts.collect::<Vec<_>>(),
+            }),
+        };
+        Ok(response?)
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+/// Keys and config of the account reads that can be served from the cache: default commitment,
+/// binary encodings and without data slice
+fn parse_account_request(
+    request: RpcRequest,
+    params: &Value,
+) -> Option<(Vec<Pubkey>, RpcAccountInfoConfig)> {
+    let keys = match (request, params.get(0)?) {
+        (RpcRequest::GetAccountInfo, Value::String(key)) => vec![Pubkey::from_str(key).ok()?],
+        (RpcRequest::GetMultipleAccounts, Value::Array(keys)) => keys
+            .iter()
+            .map(|k| Pubkey::from_str(k.as_str()?).ok())
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    let config = match params.get(1) {
+        None | Some(Value::Null) => RpcAccountInfoConfig::default(),
+        Some(config) => serde_json::from_value::<RpcAccountInfoConfig>(config.clone()).ok()?,
+    };
+    let cacheable = config.commitment.unwrap_or_default() == CommitmentConfig::default()
+        && config.data_slice.is_none()
+        && config.min_context_slot.is_none()
+        && config.encoding != Some(UiAccountEncoding::JsonParsed);
+    cacheable.then_some((keys, config))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_account_cache() {
+        let cache = AccountCache::new(1, Duration::from_secs(60));
+        let (a, b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let missing = CachedAccount {
+            slot: 1,
+            account: None,
+        };
+        let existing = CachedAccount {
+            slot: 2,
+            account: Some(Account::default()),
+        };
+        assert_eq!(cache.get("endpoint", &a), None);
+        cache.insert("endpoint", a, missing.clone());
+        assert_eq!(cache.get("endpoint", &a), Some(missing.clone()));
+        assert_eq!(cache.get("other", &a), None);
+        cache.insert("endpoint", b, existing.clone());
+        assert_eq!(cache.get("endpoint", &a), None);
+        assert_eq!(cache.get("endpoint", &b), Some(existing));
+
+        let cache = AccountCache::new(1, Duration::ZERO);
+        cache.insert("endpoint", a, missing);
+        assert_eq!(cache.get("endpoint", &a), None);
+    }
+
+    #[test]
+    fn test_parse_account_request() {
+        let key = Pubkey::new_unique();
+        let params =
+            json!([key.to_string(), { "encoding": "base64+zstd", "commitment": "finalized" }]);
+        let (keys, config) = parse_account_request(RpcRequest::GetAccountInfo, &params).unwrap();
+        assert_eq!(keys, vec![key]);
+        assert_eq!(config.encoding, Some(UiAccountEncoding::Base64Zstd));
+
+        let params = json!([[key.to_string()]]);
+        let (keys, _) = parse_account_request(RpcRequest::GetMultipleAccounts, &params).unwrap();
+        assert_eq!(keys, vec![key]);
+
+        for config in [
+            json!({ "commitment": "processed" }),
+            json!({ "minContextSlot": 1 }),
+            json!({ "dataSlice": { "offset": 0, "length": 32 } }),
+            json!({ "encoding": "jsonParsed" }),
+        ] {
+            let params = json!([key.to_string(), config]);
+            assert!(parse_account_request(RpcRequest::GetAccountInfo, &params).is_none());
+        }
+        let params = json!([key.to_string()]);
+        assert!(parse_account_request(RpcRequest::GetBalance, &params).is_none());
+    }
+}
@@ -1,7 +1,39 @@
+use std::str::FromStr;
+
+use solana_sdk::pubkey::Pubkey;
+
+pub const DEFAULT_DNS_TTL: u32 = 300;
+pub const DEFAULT_CACHE_TTL: u64 = 5;
+pub const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+pub const DEFAULT_WEB_CACHE_TTL: u32 = 300;
+pub const DEFAULT_REST_CACHE_TTL: u32 = 30;
+pub const DEFAULT_WS_MAX_SUBSCRIPTIONS: usize = 100;
+pub const DEFAULT_WS_MAX_CONNECTIONS: usize = 10;
+pub const DEFAULT_IPFS_GATEWAY: &str = "https://ipfs.io";
+pub const DEFAULT_ARWEAVE_GATEWAY: &str = "https://arweave.net";
+pub const DEFAULT_SHDW_GATEWAY: &str = "https://shdw-drive.genesysgo.net";
+// Pyth price accounts keyed by token mint, as `<mint>:<price account>` pairs
+pub const DEFAULT_PRICE_FEEDS: &str = "\
+    EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v:Gnt27xtC473ZT2Mw5u8wZ68Z3gULkSTb5DuxJy7eJotD,\
+    So11111111111111111111111111111111111111112:H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG,\
+    Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB:3vxLXJqLqF3JG5TCbYycbKWRBbCJQLxQmBGCkyqEEefL,\
+    DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263:8ihFLu5FimgTQ1Unh4dVyEHUGodJ5gJQCrQf4KUVB9bN,\
+    mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So:E4v1BBgoso9s64TQvmyownAVJbhbEPGyzA3qn4n46qj9,\
+    EchesyfXePKdLtoiZSL8pBe8Myagyy8ZRqsACNCFGnvp:ETp9eKXVv1dWwHSpsXRUuXHmw24PwRkttCGVgpZEY9zF";
+// USD prices by label length, the last one applies to all longer labels
+pub const DEFAULT_USD_PRICES: &str = "750,700,640,160,20";
+pub const DEFAULT_MAX_PRICE_AGE: u64 = 60;
+
 lazy_static::lazy_static! {
     pub static ref CONFIG: Config = Config::from_env();
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebGatewayMode {
+    Redirect,
+    Proxy,
+}
+
 pub struct Config {
     pub quicknode_username: String,
     pub quicknode_password: String,
@@ -10,6 +42,22 @@ pub struct Config {
     pub postgres_password: String,
     pub postgres_port: u16,
     pub mattermost_hook: Option<String>,
+    pub dns_ttl: u32,
+    pub dns_bind_address: Option<String>,
+    pub dns_rpc_url: Option<String>,
+    pub cache_ttl: u64,
+    pub cache_capacity: usize,
+    pub web_gateway_mode: WebGatewayMode,
+    pub web_cache_ttl: u32,
+    pub ipfs_gateway: String,
+    pub arweave_gateway: String,
+    pub shdw_gateway: String,
+    pub rest_cache_ttl: u32,
+    pub ws_max_subscriptions: usize,
+    pub ws_max_connections: usize,
+    pub price_feeds: Vec<(Pubkey, Pubkey)>,
+    pub usd_prices: Vec<u64>,
+    pub max_price_age: u64,
 }
 
 impl Config {
@@ -22,6 +70,109 @@ impl Config {
             postgres_port: std::env::var("POSTGRES_PORT").unwrap().parse().unwrap(),
             port: std::env::var("BIND_PORT").unwrap().parse().unwrap(),
             mattermost_hook: std::env::var("MATTERMOST_HOOK").ok(),
+            dns_ttl: std::env::var("DNS_TTL")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_DNS_TTL),
+            dns_bind_address: std::env::var("DNS_BIND_ADDRESS").ok(),
+            dns_rpc_url: std::env::var("DNS_RPC_URL").ok(),
+            cache_ttl: std::env::var("CACHE_TTL")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_CACHE_TTL),
+            cache_capacity: std::env::var("CACHE_CAPACITY")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_CACHE_CAPACITY),
+            web_gateway_mode: match std::env::var("WEB_GATEWAY_MODE").as_deref() {
+                Ok("proxy") => WebGatewayMode::Proxy,
+                Ok("redirect") | Err(_) => WebGatewayMode::Redirect,
+                Ok(mode) => panic!("Invalid WEB_GATEWAY_MODE {mode}"),
+            },
+            web_cache_ttl: std::env::var("WEB_CACHE_TTL")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_WEB_CACHE_TTL),
+            ipfs_gateway: gateway_var("IPFS_GATEWAY", DEFAULT_IPFS_GATEWAY),
+            arweave_gateway: gateway_var("ARWEAVE_GATEWAY", DEFAULT_ARWEAVE_GATEWAY),
+            shdw_gateway: gateway_var("SHDW_GATEWAY", DEFAULT_SHDW_GATEWAY),
+            rest_cache_ttl: std::env::var("REST_CACHE_TTL")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_REST_CACHE_TTL),
+            ws_max_subscriptions: std::env::var("WS_MAX_SUBSCRIPTIONS")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_WS_MAX_SUBSCRIPTIONS),
+            ws_max_connections: std::env::var("WS_MAX_CONNECTIONS")
+                .map(|s| s.parse().unwrap())
+                .unwrap_or(DEFAULT_WS_MAX_CONNECTIONS),
+            price_feeds: parse_price_feeds(
+                &std::env::var("PRICE_FEEDS").unwrap_or_else(|_| DEFAULT_PRICE_FEEDS.to_owned()),
+            ),
+            usd_prices: parse_usd_prices(
+                &std::env::var("USD_PRICES").unwrap_or_else(|_| DEFAULT_USD_PRICES.to_owned()),
+            ),
+            max_price_age: std::env::var("MAX_PRICE_AGE")
+                .map(|s| s.parse().expect("Invalid MAX_PRICE_AGE"))
+                .unwrap_or(DEFAULT_MAX_PRICE_AGE),
         }
     }
 }
+
+fn gateway_var(name: &str, default: &str) -> String {
+    std::env::var(name)
+        .unwrap_or_else(|_| default.to_owned())
+        .trim_end_matches('/')
+        .to_owned()
+}
+
+fn parse_price_feeds(value: &str) -> Vec<(Pubkey, Pubkey)> {
+    value
+        .split(',')
+        .map(|pair| {
+            let (mint, feed) = pair
+                .trim()
+                .split_once(':')
+                .unwrap_or_else(|| panic!("Invalid PRICE_FEEDS entry {pair}"));
+            (
+                Pubkey::from_str(mint)
+                    .unwrap_or_else(|_| panic!("Invalid PRICE_FEEDS mint {mint}")),
+                Pubkey::from_str(feed)
+                    .unwrap_or_else(|_| panic!("Invalid PRICE_FEEDS price account {feed}")),
+            )
+        })
+        .collect()
+}
+
+fn parse_usd_prices(value: &str) -> Vec<u64> {
+    let prices = value
+        .split(',')
+        .map(|s| {
+            s.trim()
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid USD_PRICES entry {s}"))
+        })
+        .collect::<Vec<_>>();
+    assert!(!prices.is_empty(), "USD_PRICES cannot be empty");
+    prices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_defaults() {
+        let feeds = parse_price_feeds(DEFAULT_PRICE_FEEDS);
+        assert_eq!(feeds.len(), 6);
+        assert_eq!(
+            feeds[0].0.to_string(),
+            "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v"
+        );
+        assert_eq!(
+            parse_usd_prices(DEFAULT_USD_PRICES),
+            [750, 700, 640, 160, 20]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid PRICE_FEEDS mint")]
+    fn test_invalid_price_feeds() {
+        parse_price_feeds("mint:H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG");
+    }
+}
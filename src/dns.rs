@@ -0,0 +1,270 @@
+use std::str::FromStr;
+
+use actix_web::{
+    get,
+    http::header::{self, HeaderValue},
+    post, web, HttpMessage, HttpRequest, HttpResponse,
+};
+use base64::Engine;
+use hickory_proto::{
+    op::{Edns, Message, MessageType, OpCode, Query, ResponseCode},
+    rr::{Name, RecordType},
+};
+use resolver::RecordSource;
+use serde::{Deserialize, Serialize};
+
+use crate::{append_trace, config::CONFIG, db::DbConnector, sns::get_rpc_client, trace, ErrorType};
+
+pub mod resolver;
+pub mod server;
+
+pub const DNS_MESSAGE_CONTENT_TYPE: &str = "application/dns-message";
+pub const DNS_JSON_CONTENT_TYPE: &str = "application/dns-json";
+pub const EDNS_MAX_PAYLOAD: u16 = 4096;
+
+#[derive(Deserialize)]
+pub struct QueryParams {
+    dns: Option<String>,
+    name: Option<String>,
+    #[serde(rename = "type")]
+    record_type: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct JsonResponse {
+    status: u16,
+    #[serde(rename = "TC")]
+    tc: bool,
+    #[serde(rename = "RD")]
+    rd: bool,
+    #[serde(rename = "RA")]
+    ra: bool,
+    #[serde(rename = "AD")]
+    ad: bool,
+    #[serde(rename = "CD")]
+    cd: bool,
+    question: Vec<JsonQuestion>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    answer: Vec<JsonAnswer>,
+}
+
+#[derive(Serialize)]
+pub struct JsonQuestion {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: u16,
+}
+
+#[derive(Serialize)]
+pub struct JsonAnswer {
+    name: String,
+    #[serde(rename = "type")]
+    record_type: u16,
+    #[serde(rename = "TTL")]
+    ttl: u32,
+    data: String,
+}
+
+#[get("/dns-query")]
+pub async fn query_get(
+    request: HttpRequest,
+    params: web::Query<QueryParams>,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    match (&params.dns, &params.name) {
+        (Some(dns), _) => {
+            let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+                .decode(dns.trim_end_matches('='))
+                .map_err(|e| trace!(ErrorType::MalformedRequest, e))?;
+            process_message(&request, &db, &bytes)
+                .await
+                .map_err(|e| append_trace!(e))
+        }
+        (None, Some(name)) => process_json(&request, &db, name, params.record_type.as_deref())
+            .await
+            .map_err(|e| append_trace!(e)),
+        (None, None) => Err(trace!(ErrorType::MissingParameters)),
+    }
+}
+
+#[post("/dns-query")]
+pub async fn query_post(
+    request: HttpRequest,
+    body: web::Bytes,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    if !is_dns_message(&request) {
+        let content_type = request.headers().get(header::CONTENT_TYPE);
+        return Err(trace!(ErrorType::MalformedRequest, content_type));
+    }
+    process_message(&request, &db, &body)
+        .await
+        .map_err(|e| append_trace!(e))
+}
+
+// Only the essence of the media type is compared, parameters such as `charset` are ignored
+fn is_dns_message(request: &HttpRequest) -> bool {
+    match request.mime_type() {
+        Ok(Some(mime)) => mime.essence_str() == DNS_MESSAGE_CONTENT_TYPE,
+        _ => false,
+    }
+}
+
+async fn process_message(
+    request: &HttpRequest,
+    db: &DbConnector,
+    bytes: &[u8],
+) -> Result<HttpResponse, crate::Error> {
+    let message = Message::from_vec(bytes).map_err(|e| trace!(ErrorType::MalformedRequest, e))?;
+    let rpc_client = get_rpc_client(db, request, None)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let (response, ttl) = answer_message(&rpc_client, &message, CONFIG.dns_ttl).await;
+    let body = response
+        .to_vec()
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    Ok(HttpResponse::Ok()
+        .content_type(DNS_MESSAGE_CONTENT_TYPE)
+        .insert_header(cache_control(ttl))
+        .body(body))
+}
+
+async fn process_json(
+    request: &HttpRequest,
+    db: &DbConnector,
+    name: &str,
+    record_type: Option<&str>,
+) -> Result<HttpResponse, crate::Error> {
+    let name = Name::from_utf8(name).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let record_type = match record_type {
+        None => RecordType::A,
+        Some(t) => match t.parse::<u16>() {
+            Ok(n) => RecordType::from(n),
+            Err(_) => RecordType::from_str(&t.to_uppercase())
+                .map_err(|e| trace!(ErrorType::InvalidParameters, e))?,
+        },
+    };
+    let mut message = Message::new();
+    message
+        .set_recursion_desired(true)
+        .add_query(Query::query(name, record_type));
+    let rpc_client = get_rpc_client(db, request, None)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let (response, ttl) = answer_message(&rpc_client, &message, CONFIG.dns_ttl).await;
+    let body = JsonResponse {
+        status: response.response_code().into(),
+        tc: response.truncated(),
+        rd: response.recursion_desired(),
+        ra: response.recursion_available(),
+        ad: false,
+        cd: false,
+        question: response
+            .queries()
+            .iter()
+            .map(|q| JsonQuestion {
+                name: q.name().to_utf8(),
+                record_type: q.query_type().into(),
+            })
+            .collect(),
+        answer: response
+            .answers()
+            .iter()
+            .map(|r| JsonAnswer {
+                name: r.name().to_utf8(),
+                record_type: r.record_type().into(),
+                ttl: r.ttl(),
+                data: r.data().map(|d| d.to_string()).unwrap_or_default(),
+            })
+            .collect(),
+    };
+    Ok(HttpResponse::Ok()
+        .content_type(DNS_JSON_CONTENT_TYPE)
+        .insert_header(cache_control(ttl))
+        .json(body))
+}
+
+pub async fn answer_message<S: RecordSource>(
+    source: &S,
+    message: &Message,
+    ttl: u32,
+) -> (Message, u32) {
+    let mut response = Message::new();
+    response
+        .set_id(message.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(message.op_code())
+        .set_recursion_desired(message.recursion_desired())
+        .add_queries(message.queries().to_vec());
+    if message.extensions().is_some() {
+        let mut edns = Edns::new();
+        edns.set_max_payload(EDNS_MAX_PAYLOAD);
+        response.set_edns(edns);
+    }
+    let query = match message.queries() {
+        [query] if message.message_type() == MessageType::Query => query,
+        _ => {
+            response.set_response_code(ResponseCode::FormErr);
+            return (response, ttl);
+        }
+    };
+    if message.op_code() != OpCode::Query {
+        response.set_response_code(ResponseCode::NotImp);
+        return (response, ttl);
+    }
+
+    match resolver::resolve(source, query.name(), query.query_type(), ttl).await {
+        Ok(answer) => {
+            let answer_ttl = answer.ttl(ttl);
+            response
+                .set_authoritative(answer.response_code != ResponseCode::Refused)
+                .set_response_code(answer.response_code)
+                .add_answers(answer.records);
+            (response, answer_ttl)
+        }
+        Err(e) => {
+            log::error!("Failed to resolve {}: {e:?}", query.name());
+            response.set_response_code(ResponseCode::ServFail);
+            (response, 0)
+        }
+    }
+}
+
+fn cache_control(ttl: u32) -> (header::HeaderName, HeaderValue) {
+    let value = if ttl == 0 {
+        HeaderValue::from_static("no-store")
+    } else {
+        HeaderValue::from_str(&format!("max-age={ttl}"))
+            .unwrap_or(HeaderValue::from_static("no-store"))
+    };
+    (header::CACHE_CONTROL, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+
+    use super::*;
+
+    #[test]
+    fn test_is_dns_message() {
+        for content_type in [
+            "application/dns-message",
+            "Application/DNS-Message",
+            "application/dns-message; charset=utf-8",
+        ] {
+            let request = TestRequest::default()
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .to_http_request();
+            assert!(is_dns_message(&request), "{content_type}");
+        }
+        for content_type in ["application/dns-json", "application/dns-messages", "dns"] {
+            let request = TestRequest::default()
+                .insert_header((header::CONTENT_TYPE, content_type))
+                .to_http_request();
+            assert!(!is_dns_message(&request), "{content_type}");
+        }
+        assert!(!is_dns_message(&TestRequest::default().to_http_request()));
+    }
+}
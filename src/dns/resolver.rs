@@ -0,0 +1,296 @@
+use std::{
+    future::Future,
+    net::{Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use crate::{append_trace, trace, ErrorType};
+use hickory_proto::{
+    op::ResponseCode,
+    rr::{
+        rdata::{A, AAAA, CNAME, TXT},
+        Name, RData, Record as DnsRecord, RecordType,
+    },
+};
+use sns_sdk::{
+    derivation::get_domain_key,
+    record::{Record, RecordVersion},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::program_pack::Pack;
+use spl_name_service::state::NameRecordHeader;
+
+use crate::sns::{
+    domain_name::DomainName,
+    get_domain_data::{format_v1_data, parse_record_v2, Encoding, QueryResult, ValidationIdFormat},
+    get_multiple_accounts_batched, get_record_key_from_domain_key,
+};
+
+const SOL_TLD: &str = "sol.";
+const MAX_TXT_CHUNK_LENGTH: usize = 255;
+
+pub trait RecordSource {
+    /// Returns `None` when the domain does not exist
+    fn get_records(
+        &self,
+        domain: &str,
+        records: &[Record],
+    ) -> impl Future<Output = Result<Option<Vec<Option<String>>>, crate::Error>> + Send;
+}
+
+impl RecordSource for RpcClient {
+    async fn get_records(
+        &self,
+        domain: &str,
+        records: &[Record],
+    ) -> Result<Option<Vec<Option<String>>>, crate::Error> {
+        let domain_key = get_domain_key(domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+        let mut keys = vec![domain_key];
+        for record in records {
+            keys.push(get_record_key_from_domain_key(
+                &domain_key,
+                *record,
+                RecordVersion::V2,
+            ));
+            keys.push(get_record_key_from_domain_key(
+                &domain_key,
+                *record,
+                RecordVersion::V1,
+            ));
+        }
+        let mut accounts = get_multiple_accounts_batched(self, &keys)
+            .await
+            .map_err(|e| append_trace!(e))?
+            .into_iter();
+        let Some(domain_account) = accounts.next().flatten() else {
+            return Ok(None);
+        };
+        let domain_header =
+            NameRecordHeader::unpack_unchecked(&domain_account.data[..NameRecordHeader::LEN])
+                .map_err(|e| trace!(ErrorType::Generic, e))?;
+
+        let mut result = Vec::with_capacity(records.len());
+        for (record, v1_key) in records.iter().zip(keys.iter().skip(2).step_by(2)) {
+            let v2 = accounts.next().flatten().and_then(|a| {
+                match parse_record_v2(
+                    &domain_header.owner,
+                    &a.data,
+                    *record,
+                    ValidationIdFormat::String,
+                ) {
+                    Ok(QueryResult::V2 {
+                        content,
+                        is_stale: false,
+                        ..
+                    }) => Some(content),
+                    _ => None,
+                }
+            });
+            let v1 = accounts.next().flatten().and_then(|a| {
+                format_v1_data(&a.data, Some((*record, *v1_key)), Encoding::Decoded).ok()
+            });
+            result.push(v2.or(v1).filter(|c| !c.is_empty()));
+        }
+        Ok(Some(result))
+    }
+}
+
+pub struct Answer {
+    pub response_code: ResponseCode,
+    pub records: Vec<DnsRecord>,
+}
+
+impl Answer {
+    fn from_code(response_code: ResponseCode) -> Self {
+        Self {
+            response_code,
+            records: vec![],
+        }
+    }
+
+    /// The TTL used for caching, the minimum over all records
+    pub fn ttl(&self, default: u32) -> u32 {
+        self.records
+            .iter()
+            .map(|r| r.ttl())
+            .min()
+            .unwrap_or(default)
+    }
+}
+
+pub async fn resolve<S: RecordSource>(
+    source: &S,
+    name: &Name,
+    record_type: RecordType,
+    ttl: u32,
+) -> Result<Answer, crate::Error> {
+    let fqdn = name.to_lowercase().to_utf8();
+    let fqdn = if fqdn.ends_with('.') {
+        fqdn
+    } else {
+        format!("{fqdn}.")
+    };
+    // The TLD itself is not a registered name, the domain key would be derived from an empty name
+    if fqdn == SOL_TLD {
+        return Ok(Answer::from_code(ResponseCode::NoError));
+    }
+    if !fqdn.ends_with(&format!(".{SOL_TLD}")) {
+        return Ok(Answer::from_code(ResponseCode::Refused));
+    }
+    let Ok(domain) = DomainName::new(&fqdn) else {
+        return Ok(Answer::from_code(ResponseCode::NXDomain));
+    };
+
+    let records: &[Record] = match record_type {
+        RecordType::A => &[Record::A, Record::CNAME],
+        RecordType::AAAA => &[Record::AAAA, Record::CNAME],
+        RecordType::CNAME => &[Record::CNAME],
+        RecordType::TXT => &[Record::TXT],
+        _ => &[],
+    };
+    let contents = match source.get_records(&domain, records).await {
+        Ok(Some(contents)) => contents,
+        Ok(None) => return Ok(Answer::from_code(ResponseCode::NXDomain)),
+        Err(e) if matches!(e.ty, ErrorType::InvalidDomain) => {
+            return Ok(Answer::from_code(ResponseCode::NXDomain))
+        }
+        Err(e) => return Err(append_trace!(e)),
+    };
+    if records.is_empty() {
+        return Ok(Answer::from_code(ResponseCode::NoError));
+    }
+
+    let answer = records
+        .iter()
+        .zip(contents)
+        .filter_map(|(record, content)| Some((*record, content?)))
+        .find_map(|(record, content)| to_rdata(record, &content));
+    let records = match answer {
+        Some(rdata) => vec![DnsRecord::from_rdata(name.clone(), ttl, rdata)],
+        None => return Ok(Answer::from_code(ResponseCode::NXDomain)),
+    };
+    Ok(Answer {
+        response_code: ResponseCode::NoError,
+        records,
+    })
+}
+
+fn to_rdata(record: Record, content: &str) -> Option<RData> {
+    let content = content.trim();
+    let rdata = match record {
+        Record::A => RData::A(A(Ipv4Addr::from_str(content).ok()?)),
+        Record::AAAA => RData::AAAA(AAAA(Ipv6Addr::from_str(content).ok()?)),
+        Record::CNAME => {
+            let target = format!("{}.", content.trim_end_matches('.'));
+            RData::CNAME(CNAME(Name::from_utf8(target).ok()?))
+        }
+        Record::TXT => RData::TXT(TXT::new(split_txt(content))),
+        _ => return None,
+    };
+    Some(rdata)
+}
+
+// TXT character strings are limited to 255 bytes each
+fn split_txt(content: &str) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+    for c in content.chars() {
+        if current.len() + c.len_utf8() > MAX_TXT_CHUNK_LENGTH {
+            chunks.push(std::mem::take(&mut current));
+        }
+        current.push(c);
+    }
+    chunks.push(current);
+    chunks
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Default)]
+    pub struct MockSource {
+        pub domains: HashMap<String, HashMap<&'static str, String>>,
+    }
+
+    impl RecordSource for MockSource {
+        async fn get_records(
+            &self,
+            domain: &str,
+            records: &[Record],
+        ) -> Result<Option<Vec<Option<String>>>, crate::Error> {
+            Ok(self.domains.get(domain).map(|contents| {
+                records
+                    .iter()
+                    .map(|r| contents.get(r.as_str()).cloned())
+                    .collect()
+            }))
+        }
+    }
+
+    pub fn mock_source() -> MockSource {
+        let mut source = MockSource::default();
+        source.domains.insert(
+            "bonfida".to_owned(),
+            HashMap::from([("A", "1.2.3.4".to_owned()), ("TXT", "a".repeat(300))]),
+        );
+        source.domains.insert(
+            "www.bonfida".to_owned(),
+            HashMap::from([("CNAME", "bonfida.sol".to_owned())]),
+        );
+        source
+    }
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let source = mock_source();
+        let name = Name::from_utf8("bonfida.sol.").unwrap();
+
+        let answer = resolve(&source, &name, RecordType::A, 60).await.unwrap();
+        assert_eq!(answer.response_code, ResponseCode::NoError);
+        assert_eq!(
+            answer.records[0].data(),
+            Some(&RData::A(A(Ipv4Addr::new(1, 2, 3, 4))))
+        );
+        assert_eq!(answer.ttl(300), 60);
+
+        let answer = resolve(&source, &name, RecordType::TXT, 60).await.unwrap();
+        let Some(RData::TXT(txt)) = answer.records[0].data() else {
+            panic!()
+        };
+        assert_eq!(txt.txt_data().len(), 2);
+
+        let answer = resolve(&source, &name, RecordType::AAAA, 60).await.unwrap();
+        assert_eq!(answer.response_code, ResponseCode::NXDomain);
+
+        let name = Name::from_utf8("WWW.Bonfida.sol").unwrap();
+        let answer = resolve(&source, &name, RecordType::A, 60).await.unwrap();
+        assert_eq!(answer.records[0].record_type(), RecordType::CNAME);
+
+        let name = Name::from_utf8("unknown.sol.").unwrap();
+        let answer = resolve(&source, &name, RecordType::A, 60).await.unwrap();
+        assert_eq!(answer.response_code, ResponseCode::NXDomain);
+
+        let name = Name::from_utf8("bonfida.com.").unwrap();
+        let answer = resolve(&source, &name, RecordType::A, 60).await.unwrap();
+        assert_eq!(answer.response_code, ResponseCode::Refused);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tld() {
+        let mut source = mock_source();
+        // Would be returned if the TLD were looked up as a domain
+        source.domains.insert(
+            "sol".to_owned(),
+            HashMap::from([("A", "1.2.3.4".to_owned())]),
+        );
+        for name in ["sol.", "SOL"] {
+            let name = Name::from_utf8(name).unwrap();
+            let answer = resolve(&source, &name, RecordType::A, 60).await.unwrap();
+            assert_eq!(answer.response_code, ResponseCode::NoError);
+            assert!(answer.records.is_empty());
+        }
+    }
+}
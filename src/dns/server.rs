@@ -0,0 +1,276 @@
+use std::{sync::Arc, time::Duration};
+
+use hickory_proto::op::{Message, MessageType, ResponseCode};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+    sync::Semaphore,
+    time::timeout,
+};
+
+use super::{answer_message, resolver::RecordSource, EDNS_MAX_PAYLOAD};
+
+// Maximum UDP payload without EDNS0 (RFC 1035)
+const DEFAULT_UDP_PAYLOAD: u16 = 512;
+// Maximum number of UDP queries and TCP connections handled at once
+const MAX_CONCURRENT_TASKS: usize = 1_024;
+// TCP connections are closed when no complete message is received within this delay
+const TCP_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub async fn bind(address: &str) -> std::io::Result<(UdpSocket, TcpListener)> {
+    let udp = UdpSocket::bind(address).await?;
+    let tcp = TcpListener::bind(udp.local_addr()?).await?;
+    Ok((udp, tcp))
+}
+
+pub async fn serve<S: RecordSource + Send + Sync + 'static>(
+    udp: UdpSocket,
+    tcp: TcpListener,
+    source: Arc<S>,
+    ttl: u32,
+) {
+    let tasks = Arc::new(Semaphore::new(MAX_CONCURRENT_TASKS));
+    futures::join!(
+        serve_udp(udp, Arc::clone(&source), Arc::clone(&tasks), ttl),
+        serve_tcp(tcp, source, tasks, ttl)
+    );
+}
+
+async fn serve_udp<S: RecordSource + Send + Sync + 'static>(
+    socket: UdpSocket,
+    source: Arc<S>,
+    tasks: Arc<Semaphore>,
+    ttl: u32,
+) {
+    let socket = Arc::new(socket);
+    let mut buffer = vec![0; EDNS_MAX_PAYLOAD as usize];
+    loop {
+        let (len, peer) = match socket.recv_from(&mut buffer).await {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("Failed to receive DNS datagram: {e:?}");
+                continue;
+            }
+        };
+        // Datagrams queue up in the socket buffer, and are eventually dropped, while all tasks
+        // are busy
+        let Ok(permit) = Arc::clone(&tasks).acquire_owned().await else {
+            return;
+        };
+        let request = buffer[..len].to_vec();
+        let socket = Arc::clone(&socket);
+        let source = Arc::clone(&source);
+        tokio::spawn(async move {
+            let _permit = permit;
+            let Some(response) = handle_request(source.as_ref(), &request, ttl, true).await else {
+                return;
+            };
+            if let Err(e) = socket.send_to(&response, peer).await {
+                log::error!("Failed to send DNS response to {peer}: {e:?}");
+            }
+        });
+    }
+}
+
+async fn serve_tcp<S: RecordSource + Send + Sync + 'static>(
+    listener: TcpListener,
+    source: Arc<S>,
+    tasks: Arc<Semaphore>,
+    ttl: u32,
+) {
+    loop {
+        let Ok(permit) = Arc::clone(&tasks).acquire_owned().await else {
+            return;
+        };
+        let (stream, peer) = match listener.accept().await {
+            Ok(r) => r,
+            Err(e) => {
+                log::error!("Failed to accept DNS connection: {e:?}");
+                continue;
+            }
+        };
+        let source = Arc::clone(&source);
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = handle_connection(stream, source.as_ref(), ttl).await {
+                log::warn!("DNS connection from {peer} closed: {e:?}");
+            }
+        });
+    }
+}
+
+// Messages over TCP are prefixed with their length as a big endian u16 (RFC 1035 4.2.2)
+async fn handle_connection<S: RecordSource>(
+    mut stream: TcpStream,
+    source: &S,
+    ttl: u32,
+) -> std::io::Result<()> {
+    loop {
+        let request = async {
+            let len = stream.read_u16().await?;
+            let mut request = vec![0; len as usize];
+            stream.read_exact(&mut request).await?;
+            Ok::<_, std::io::Error>(request)
+        };
+        let request = match timeout(TCP_IDLE_TIMEOUT, request).await {
+            Ok(Ok(request)) => request,
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+            Err(_) => return Ok(()),
+        };
+        let Some(response) = handle_request(source, &request, ttl, false).await else {
+            return Ok(());
+        };
+        // Encoded messages are truncated to the maximum length, larger responses are dropped
+        // rather than sent with a wrapped length prefix
+        let len = u16::try_from(response.len()).map_err(|_| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "DNS response too large")
+        })?;
+        stream.write_u16(len).await?;
+        stream.write_all(&response).await?;
+    }
+}
+
+async fn handle_request<S: RecordSource>(
+    source: &S,
+    request: &[u8],
+    ttl: u32,
+    is_udp: bool,
+) -> Option<Vec<u8>> {
+    // Malformed messages are dropped, answering them would let spoofed datagrams reflect
+    // responses to third parties
+    let message = match Message::from_vec(request) {
+        Ok(message) => message,
+        Err(e) => {
+            log::debug!("Dropping malformed DNS message: {e:?}");
+            return None;
+        }
+    };
+    if message.message_type() != MessageType::Query {
+        return None;
+    }
+    let (response, _) = answer_message(source, &message, ttl).await;
+    let bytes = match response.to_vec() {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            log::error!("Failed to encode DNS response: {e:?}");
+            let response =
+                Message::error_msg(message.id(), message.op_code(), ResponseCode::ServFail);
+            return response.to_vec().ok();
+        }
+    };
+    if !is_udp {
+        return Some(bytes);
+    }
+    let max_payload = message
+        .extensions()
+        .as_ref()
+        .map_or(DEFAULT_UDP_PAYLOAD, |edns| {
+            edns.max_payload()
+                .clamp(DEFAULT_UDP_PAYLOAD, EDNS_MAX_PAYLOAD)
+        });
+    if bytes.len() <= max_payload as usize {
+        return Some(bytes);
+    }
+    // The client is expected to retry over TCP
+    let mut truncated = response.truncate();
+    truncated.add_queries(response.queries().to_vec());
+    truncated.to_vec().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::resolver::tests::mock_source;
+    use hickory_proto::{
+        op::{Edns, Query},
+        rr::{rdata::A, Name, RData, RecordType},
+    };
+    use std::net::Ipv4Addr;
+
+    fn query(name: &str, record_type: RecordType, edns: bool) -> Message {
+        let mut message = Message::new();
+        message
+            .set_id(42)
+            .set_recursion_desired(true)
+            .add_query(Query::query(Name::from_utf8(name).unwrap(), record_type));
+        if edns {
+            let mut edns = Edns::new();
+            edns.set_max_payload(1232);
+            message.set_edns(edns);
+        }
+        message
+    }
+
+    #[tokio::test]
+    async fn test_udp_and_tcp() {
+        let (udp, tcp) = bind("127.0.0.1:0").await.unwrap();
+        let address = udp.local_addr().unwrap();
+        tokio::spawn(serve(udp, tcp, Arc::new(mock_source()), 60));
+
+        let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let request = query("bonfida.sol.", RecordType::A, true);
+        client
+            .send_to(&request.to_vec().unwrap(), address)
+            .await
+            .unwrap();
+        let mut buffer = vec![0; 4096];
+        let len = client.recv(&mut buffer).await.unwrap();
+        let response = Message::from_vec(&buffer[..len]).unwrap();
+        assert_eq!(response.id(), 42);
+        assert!(response.authoritative());
+        assert_eq!(response.extensions().as_ref().unwrap().max_payload(), 4096);
+        assert_eq!(
+            response.answers()[0].data(),
+            Some(&RData::A(A(Ipv4Addr::new(1, 2, 3, 4))))
+        );
+
+        let request = query("bonfida.sol.", RecordType::TXT, false);
+        let request = request.to_vec().unwrap();
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream.write_u16(request.len() as u16).await.unwrap();
+        stream.write_all(&request).await.unwrap();
+        let len = stream.read_u16().await.unwrap();
+        let mut buffer = vec![0; len as usize];
+        stream.read_exact(&mut buffer).await.unwrap();
+        let response = Message::from_vec(&buffer).unwrap();
+        assert_eq!(response.answers().len(), 1);
+
+        let mut source = mock_source();
+        source
+            .domains
+            .get_mut("bonfida")
+            .unwrap()
+            .insert("TXT", "a".repeat(1000));
+        let response = handle_request(&source, &request, 60, true).await.unwrap();
+        let response = Message::from_vec(&response).unwrap();
+        assert!(response.truncated());
+        assert!(response.answers().is_empty());
+        assert_eq!(response.queries().len(), 1);
+        let response = handle_request(&source, &request, 60, false).await.unwrap();
+        let response = Message::from_vec(&response).unwrap();
+        assert!(!response.truncated());
+
+        // Over TCP, responses beyond the length prefix are truncated as well
+        source
+            .domains
+            .get_mut("bonfida")
+            .unwrap()
+            .insert("TXT", "a".repeat(70_000));
+        let response = handle_request(&source, &request, 60, false).await.unwrap();
+        assert!(u16::try_from(response.len()).is_ok());
+        let response = Message::from_vec(&response).unwrap();
+        assert!(response.truncated());
+
+        let request = query("unknown.sol.", RecordType::A, false);
+        let response = handle_request(&mock_source(), &request.to_vec().unwrap(), 60, true)
+            .await
+            .unwrap();
+        let response = Message::from_vec(&response).unwrap();
+        assert_eq!(response.response_code(), ResponseCode::NXDomain);
+
+        assert!(handle_request(&mock_source(), &[0, 42, 1], 60, true)
+            .await
+            .is_none());
+    }
+}
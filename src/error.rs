@@ -2,7 +2,7 @@ use std::fmt::Display;
 
 use actix_web::{
     http::{
-        header::{HeaderValue, CONTENT_TYPE},
+        header::{HeaderValue, CACHE_CONTROL, CONTENT_TYPE},
         StatusCode,
     },
     ResponseError,
@@ -28,6 +28,9 @@ pub enum ErrorType {
     ReverseRecordNotFound,
     InvalidRecord,
     InvalidRecordVersion,
+    MinContextSlotNotReached,
+    SubscriptionLimitReached,
+    ConnectionLimitReached,
 }
 
 #[derive(Debug)]
@@ -35,6 +38,7 @@ pub struct Error {
     pub ty: ErrorType,
     pub trace: Vec<String>,
     pub info: Vec<String>,
+    pub data: Option<serde_json::Value>,
 }
 
 impl Display for Error {
@@ -52,6 +56,9 @@ impl Display for Error {
             ErrorType::ReverseRecordNotFound => "Failed to find a reverse record for a domain",
             ErrorType::InvalidRecord => "The given record type is unsupported",
             ErrorType::InvalidRecordVersion => "The given record version is unsupported",
+            ErrorType::MinContextSlotNotReached => "Minimum context slot has not been reached",
+            ErrorType::SubscriptionLimitReached => "Subscription limit reached",
+            ErrorType::ConnectionLimitReached => "Connection limit reached",
             _ => "Internal error",
         };
         f.write_str(s)
@@ -76,7 +83,11 @@ impl ResponseError for Error {
             | ErrorType::MissingParameters
             | ErrorType::InvalidDomain
             | ErrorType::InvalidRecord
-            | ErrorType::InvalidRecordVersion => StatusCode::BAD_REQUEST,
+            | ErrorType::InvalidRecordVersion
+            | ErrorType::MinContextSlotNotReached => StatusCode::BAD_REQUEST,
+            ErrorType::SubscriptionLimitReached | ErrorType::ConnectionLimitReached => {
+                StatusCode::TOO_MANY_REQUESTS
+            }
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -94,6 +105,8 @@ impl ResponseError for Error {
         }
         res.headers_mut()
             .insert(CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        res.headers_mut()
+            .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
 
         res
     }
@@ -115,6 +128,7 @@ macro_rules! trace {
             ty: $crate::Error::Generic,
             trace: vec![format!("{}:{}", file!(), line!())],
             info: vec![],
+            data: None,
         }
     };
     ($ty:expr) => {
@@ -122,6 +136,7 @@ macro_rules! trace {
             ty: $ty,
             trace: vec![format!("{}:{}", file!(), line!())],
             info: vec![],
+            data: None,
         }
     };
     ($ty:expr, $expression:expr) => {
@@ -129,6 +144,7 @@ macro_rules! trace {
             ty: $ty,
             trace: vec![format!("{}:{}", file!(), line!())],
             info: vec![format!("{:?}", $expression)],
+            data: None,
         }
     };
 }
@@ -143,6 +159,11 @@ impl Error {
         self.info.push(info);
         self
     }
+
+    pub fn with_data(mut self, data: serde_json::Value) -> Self {
+        self.data = Some(data);
+        self
+    }
 }
 
 #[macro_export]
@@ -0,0 +1,307 @@
+use std::time::Duration;
+
+use actix_web::{
+    get,
+    http::{
+        header::{self, HeaderValue},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse, HttpResponseBuilder, Scope,
+};
+use sns_sdk::record::Record;
+
+use crate::{
+    append_trace,
+    config::{WebGatewayMode, CONFIG},
+    db::DbConnector,
+    dns::resolver::RecordSource,
+    rest::VARY,
+    sns::{domain_name::DomainName, get_rpc_client},
+    trace, ErrorType,
+};
+
+// Records are resolved in this order, the first valid one is used
+const WEB_RECORDS: [Record; 4] = [Record::Ipfs, Record::Arwv, Record::Shdw, Record::Url];
+
+const PROXY_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+// Covers the whole request, including reading the body
+const PROXY_TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_PROXY_BODY_SIZE: usize = 10 * 1024 * 1024;
+
+lazy_static::lazy_static! {
+    static ref HTTP_CLIENT: reqwest::Client = reqwest::Client::builder()
+        .connect_timeout(PROXY_CONNECT_TIMEOUT)
+        .timeout(PROXY_TIMEOUT)
+        .build()
+        .unwrap();
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Target {
+    Gateway(String),
+    Url(String),
+}
+
+#[get("/{domain}")]
+async fn root(
+    request: HttpRequest,
+    domain: web::Path<String>,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    serve(&request, &db, &domain, "")
+        .await
+        .map_err(|e| append_trace!(e))
+}
+
+#[get("/{domain}/{path:.*}")]
+async fn domain_path(
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let (domain, path) = path.into_inner();
+    serve(&request, &db, &domain, &path)
+        .await
+        .map_err(|e| append_trace!(e))
+}
+
+pub fn scope() -> Scope {
+    Scope::new("web").service(root).service(domain_path)
+}
+
+async fn serve(
+    request: &HttpRequest,
+    db: &DbConnector,
+    domain: &str,
+    path: &str,
+) -> Result<HttpResponse, crate::Error> {
+    let Ok(domain) = DomainName::new(domain) else {
+        return Ok(not_found(domain));
+    };
+    if has_parent_segment(path) {
+        return Ok(not_found(&domain));
+    }
+    let rpc_client = get_rpc_client(db, request, None)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let contents = match rpc_client.get_records(&domain, &WEB_RECORDS).await {
+        Ok(contents) => contents.unwrap_or_default(),
+        Err(e) if matches!(e.ty, ErrorType::InvalidDomain) => return Ok(not_found(&domain)),
+        Err(e) => return Err(append_trace!(e)),
+    };
+    let target = WEB_RECORDS
+        .iter()
+        .zip(contents)
+        .filter_map(|(record, content)| Some((*record, content?)))
+        .find_map(|(record, content)| get_target(record, &content, path));
+    match target {
+        None => Ok(not_found(&domain)),
+        Some(Target::Gateway(url)) if CONFIG.web_gateway_mode == WebGatewayMode::Proxy => {
+            proxy(&url).await.map_err(|e| append_trace!(e))
+        }
+        // Arbitrary URLs are never proxied
+        Some(Target::Gateway(url) | Target::Url(url)) => redirect(&url),
+    }
+}
+
+fn get_target(record: Record, content: &str, path: &str) -> Option<Target> {
+    let content = content.trim();
+    if content.is_empty()
+        || content.chars().any(|c| c.is_whitespace() || c.is_control())
+        || has_parent_segment(content)
+    {
+        return None;
+    }
+    let target = match record {
+        Record::Ipfs => {
+            let (namespace, id) = if let Some(id) = content.strip_prefix("ipns://") {
+                ("ipns", id)
+            } else {
+                let id = content.strip_prefix("ipfs://").unwrap_or(content);
+                ("ipfs", id.strip_prefix("/ipfs/").unwrap_or(id))
+            };
+            Target::Gateway(format!("{}/{namespace}/{id}", CONFIG.ipfs_gateway))
+        }
+        Record::Arwv => {
+            let id = content.strip_prefix("ar://").unwrap_or(content);
+            Target::Gateway(format!("{}/{id}", CONFIG.arweave_gateway))
+        }
+        Record::Shdw => {
+            let id = content.strip_prefix("shdw://").unwrap_or(content);
+            Target::Gateway(format!("{}/{id}", CONFIG.shdw_gateway))
+        }
+        Record::Url if content.starts_with("https://") || content.starts_with("http://") => {
+            Target::Url(content.to_owned())
+        }
+        _ => return None,
+    };
+    if path.is_empty() {
+        return Some(target);
+    }
+    let append = |url: String| format!("{}/{path}", url.trim_end_matches('/'));
+    let target = match target {
+        Target::Gateway(url) => Target::Gateway(append(url)),
+        Target::Url(url) => Target::Url(append(url)),
+    };
+    Some(target)
+}
+
+// Rejects `..` in paths and record ids, including its percent encoded forms, so that a request
+// cannot escape the content of the record on the gateway
+fn has_parent_segment(s: &str) -> bool {
+    s.to_ascii_lowercase().replace("%2e", ".").contains("..")
+}
+
+// Records are read from the endpoint of the caller, successful responses are only cached per
+// endpoint and other statuses are never stored
+fn insert_cache_headers(builder: &mut HttpResponseBuilder, status: StatusCode, max_age: u32) {
+    if status.is_success() || status.is_redirection() {
+        builder
+            .insert_header((header::CACHE_CONTROL, format!("private, max-age={max_age}")))
+            .insert_header((header::VARY, VARY));
+    } else {
+        builder.insert_header((header::CACHE_CONTROL, "no-store"));
+    }
+}
+
+fn redirect(url: &str) -> Result<HttpResponse, crate::Error> {
+    let location = HeaderValue::from_str(url).map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    let mut builder = HttpResponse::Found();
+    builder.insert_header((header::LOCATION, location));
+    insert_cache_headers(&mut builder, StatusCode::FOUND, CONFIG.web_cache_ttl);
+    Ok(builder.finish())
+}
+
+async fn proxy(url: &str) -> Result<HttpResponse, crate::Error> {
+    let mut response = HTTP_CLIENT
+        .get(url)
+        .send()
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    let status = StatusCode::from_u16(response.status().as_u16())
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    let mut builder = HttpResponse::build(status);
+    if let Some(content_type) = response.headers().get(reqwest::header::CONTENT_TYPE) {
+        builder.insert_header((header::CONTENT_TYPE, content_type.as_bytes()));
+    }
+    // Proxied contents are served from our origin, they must not run scripts against it
+    builder.insert_header((header::CONTENT_SECURITY_POLICY, "sandbox"));
+    insert_cache_headers(&mut builder, status, CONFIG.web_cache_ttl);
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_PROXY_BODY_SIZE as u64)
+    {
+        return Err(trace!(
+            ErrorType::Generic,
+            "The proxied content is too large"
+        ));
+    }
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?
+    {
+        if body.len() + chunk.len() > MAX_PROXY_BODY_SIZE {
+            return Err(trace!(
+                ErrorType::Generic,
+                "The proxied content is too large"
+            ));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(builder.body(body))
+}
+
+fn not_found(domain: &str) -> HttpResponse {
+    let domain = escape_html(domain);
+    let body = format!(
+        "<!DOCTYPE html>\n<html>\n<head><title>{domain}.sol</title></head>\n<body>\n\
+         <h1>Nothing to see here</h1>\n\
+         <p>{domain}.sol does not exist or has no IPFS, Arweave, Shadow Drive or URL record.</p>\n\
+         </body>\n</html>\n"
+    );
+    HttpResponse::NotFound()
+        .content_type("text/html; charset=utf-8")
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(body)
+}
+
+fn escape_html(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<script>\"a\" & 'b'</script>"),
+            "&lt;script&gt;&quot;a&quot; &amp; &#39;b&#39;&lt;/script&gt;"
+        );
+    }
+
+    #[test]
+    fn test_url_target() {
+        assert_eq!(
+            get_target(Record::Url, "https://bonfida.org", ""),
+            Some(Target::Url("https://bonfida.org".to_owned()))
+        );
+        assert_eq!(
+            get_target(Record::Url, "https://bonfida.org/", "docs/index.html"),
+            Some(Target::Url(
+                "https://bonfida.org/docs/index.html".to_owned()
+            ))
+        );
+        assert_eq!(get_target(Record::Url, "javascript:alert(1)", ""), None);
+        assert_eq!(
+            get_target(Record::Url, "https://a.org\r\nSet-Cookie: a", ""),
+            None
+        );
+        assert_eq!(get_target(Record::Url, "", ""), None);
+    }
+
+    #[test]
+    fn test_parent_segments() {
+        assert!(has_parent_segment("../index.html"));
+        assert!(has_parent_segment("docs/%2E%2e/index.html"));
+        assert!(!has_parent_segment("docs/index.html"));
+        assert_eq!(get_target(Record::Arwv, "ar://../admin", ""), None);
+        assert_eq!(get_target(Record::Ipfs, "ipfs://%2e%2e", ""), None);
+    }
+
+    #[test]
+    fn test_cache_headers() {
+        let headers = |status| {
+            let mut builder = HttpResponse::build(status);
+            insert_cache_headers(&mut builder, status, 300);
+            builder.finish().headers().clone()
+        };
+        for status in [StatusCode::OK, StatusCode::FOUND] {
+            let headers = headers(status);
+            assert_eq!(
+                headers.get(header::CACHE_CONTROL).unwrap(),
+                "private, max-age=300"
+            );
+            assert_eq!(headers.get(header::VARY).unwrap(), VARY);
+        }
+        for status in [StatusCode::NOT_FOUND, StatusCode::BAD_GATEWAY] {
+            let headers = headers(status);
+            assert_eq!(headers.get(header::CACHE_CONTROL).unwrap(), "no-store");
+            assert!(headers.get(header::VARY).is_none());
+        }
+    }
+}
@@ -1,17 +1,25 @@
+use std::sync::Arc;
+
 use actix_web::{get, web, App, HttpServer, Responder};
 use actix_web_httpauth::extractors::basic::{self, BasicAuth};
+use cache::get_cached_rpc_client;
 use config::CONFIG;
 use db::DbConnector;
 pub use error::{Error, ErrorType};
 
 use crate::matrix::{get_matrix_client, init_matrix_client, MattermostClient};
 
+pub mod cache;
 pub mod config;
 pub mod db;
+pub mod dns;
 pub mod error;
+pub mod gateway;
 pub mod matrix;
 pub mod provisioning;
+pub mod rest;
 pub mod sns;
+pub mod ws;
 
 #[get("/hello")]
 async fn greet(auth: BasicAuth) -> impl Responder {
@@ -25,6 +33,8 @@ async fn health() -> impl Responder {
 
 pub async fn main() -> std::io::Result<()> {
     dotenv::dotenv().ok();
+    // Fails on invalid environment variables before serving any request
+    lazy_static::initialize(&CONFIG);
     init_matrix_client().await;
     pretty_env_logger::init_timed();
 
@@ -36,6 +46,17 @@ pub async fn main() -> std::io::Result<()> {
     db.init().await;
     log::info!("Connected to db");
 
+    if let Some(address) = &CONFIG.dns_bind_address {
+        let rpc_url = CONFIG
+            .dns_rpc_url
+            .clone()
+            .expect("DNS_RPC_URL must be set to run the DNS server");
+        let (udp, tcp) = dns::server::bind(address).await?;
+        log::info!("DNS server listening on {address}");
+        let source = Arc::new(get_cached_rpc_client(rpc_url));
+        actix_web::rt::spawn(dns::server::serve(udp, tcp, source, CONFIG.dns_ttl));
+    }
+
     matrix_client.send_message("Server instance successfully initialized".to_owned());
 
     HttpServer::new(move || {
@@ -48,6 +69,11 @@ pub async fn main() -> std::io::Result<()> {
             .service(health)
             .service(provisioning::scope())
             .service(sns::route)
+            .service(dns::query_get)
+            .service(dns::query_post)
+            .service(gateway::scope())
+            .service(rest::scope())
+            .service(ws::route)
     })
     .bind(("0.0.0.0", CONFIG.port))?
     .run()
@@ -0,0 +1,276 @@
+use std::{collections::HashMap, future::Future};
+
+use actix_web::{
+    get,
+    http::header::{self, HeaderValue},
+    web, HttpRequest, HttpResponse, Scope,
+};
+use serde_json::{json, Map, Value};
+use sns_sdk::{derivation::get_domain_key, record::RecordVersion};
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+use crate::{
+    append_trace,
+    config::CONFIG,
+    db::DbConnector,
+    sns::{
+        domain_name::DomainName, get_all_domains_for_owner, get_domain_data, get_domain_data_v2,
+        get_favourite_domain, get_rpc_client, resolve_domain, reverse_lookup,
+    },
+    trace, ErrorType,
+};
+
+type QueryParams = web::Query<HashMap<String, String>>;
+
+// Results depend on the endpoint of the caller, which is identified by these headers
+pub const VARY: &str = "x-quicknode-id, x-instance-id";
+
+#[get("/domains/{domain}")]
+async fn domain_info(
+    request: HttpRequest,
+    domain: web::Path<String>,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let domain = DomainName::new(&domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let key = get_domain_key(&domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let rpc_client = get_rpc_client(&db, &request, None)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let owner = resolve_domain::process(rpc_client, json!({ "domain": domain.as_str() }))
+        .await
+        .map_err(|e| append_trace!(e))?;
+    if owner["value"].is_null() {
+        return Ok(not_found());
+    }
+    let result = json!({
+        "domain": domain.as_str(),
+        "key": key.to_string(),
+        "owner": owner["value"],
+    });
+    respond(&request, result, CONFIG.rest_cache_ttl)
+}
+
+#[get("/domains/{domain}/records/{record}")]
+async fn domain_record(
+    request: HttpRequest,
+    path: web::Path<(String, String)>,
+    query: QueryParams,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let (domain, record) = path.into_inner();
+    let mut params = to_params(query.into_inner());
+    let version = params.remove("version");
+    params.insert("domain".to_owned(), Value::String(domain));
+    params.insert("record".to_owned(), Value::String(record));
+    match parse_version(version)? {
+        RecordVersion::V1 => process(&request, &db, get_domain_data::process, params).await,
+        RecordVersion::V2 => process(&request, &db, get_domain_data_v2::process, params).await,
+    }
+}
+
+// Records are read as V2 unless `version` is set
+fn parse_version(version: Option<Value>) -> Result<RecordVersion, crate::Error> {
+    let Some(version) = version else {
+        return Ok(RecordVersion::V2);
+    };
+    match version.as_u64() {
+        Some(1) => Ok(RecordVersion::V1),
+        Some(2) => Ok(RecordVersion::V2),
+        Some(v) => Err(trace!(ErrorType::InvalidRecordVersion, v)),
+        None => Err(trace!(ErrorType::InvalidParameters, version)),
+    }
+}
+
+#[get("/owners/{owner}/domains")]
+async fn owner_domains(
+    request: HttpRequest,
+    owner: web::Path<String>,
+    query: QueryParams,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let mut params = to_params(query.into_inner());
+    params.insert("owner".to_owned(), Value::String(owner.into_inner()));
+    process(&request, &db, get_all_domains_for_owner::process, params).await
+}
+
+#[get("/owners/{owner}/primary")]
+async fn owner_primary(
+    request: HttpRequest,
+    owner: web::Path<String>,
+    query: QueryParams,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let mut params = to_params(query.into_inner());
+    params.insert("owner".to_owned(), Value::String(owner.into_inner()));
+    process(&request, &db, get_favourite_domain::process, params).await
+}
+
+#[get("/keys/{domain_key}/reverse")]
+async fn reverse(
+    request: HttpRequest,
+    domain_key: web::Path<String>,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let mut params = Map::new();
+    params.insert(
+        "domainKey".to_owned(),
+        Value::String(domain_key.into_inner()),
+    );
+    process(&request, &db, reverse_lookup::process, params).await
+}
+
+pub fn scope() -> Scope {
+    Scope::new("v1")
+        .service(domain_info)
+        .service(domain_record)
+        .service(owner_domains)
+        .service(owner_primary)
+        .service(reverse)
+}
+
+async fn process<F, Fut>(
+    request: &HttpRequest,
+    db: &DbConnector,
+    process: F,
+    params: Map<String, Value>,
+) -> Result<HttpResponse, crate::Error>
+where
+    F: FnOnce(RpcClient, Value) -> Fut,
+    Fut: Future<Output = Result<Value, crate::Error>>,
+{
+    let rpc_client = get_rpc_client(db, request, None)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let result = process(rpc_client, Value::Object(params))
+        .await
+        .map_err(|e| append_trace!(e))?;
+    if result.is_null() {
+        return Ok(not_found());
+    }
+    respond(request, result, CONFIG.rest_cache_ttl)
+}
+
+// Query string values are strings, booleans and integers are converted so that they deserialize
+// like their JSON-RPC counterparts
+fn to_params(query: HashMap<String, String>) -> Map<String, Value> {
+    query
+        .into_iter()
+        .map(|(k, v)| {
+            let value = match v.as_str() {
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => v
+                    .parse::<u64>()
+                    .map(Value::from)
+                    .unwrap_or(Value::String(v)),
+            };
+            (k, value)
+        })
+        .collect()
+}
+
+fn respond(
+    request: &HttpRequest,
+    result: Value,
+    max_age: u32,
+) -> Result<HttpResponse, crate::Error> {
+    let body = serde_json::to_vec(&result).map_err(|e| trace!(ErrorType::Generic, e))?;
+    let etag = format!("\"{}\"", solana_sdk::hash::hash(&body));
+    let cache_control = format!("private, max-age={max_age}");
+    if is_not_modified(request, &etag) {
+        return Ok(HttpResponse::NotModified()
+            .insert_header((header::ETAG, etag))
+            .insert_header((header::CACHE_CONTROL, cache_control))
+            .insert_header((header::VARY, VARY))
+            .finish());
+    }
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .insert_header((header::ETAG, etag))
+        .insert_header((header::CACHE_CONTROL, cache_control))
+        .insert_header((header::VARY, VARY))
+        .body(body))
+}
+
+fn is_not_modified(request: &HttpRequest, etag: &str) -> bool {
+    request
+        .headers()
+        .get_all(header::IF_NONE_MATCH)
+        .filter_map(|h: &HeaderValue| h.to_str().ok())
+        .flat_map(|h| h.split(','))
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+fn not_found() -> HttpResponse {
+    HttpResponse::NotFound()
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .json(json!({ "error": "Not found" }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_params() {
+        let query = HashMap::from([
+            ("allowPartial".to_owned(), "true".to_owned()),
+            ("limit".to_owned(), "10".to_owned()),
+            ("cursor".to_owned(), "abc".to_owned()),
+        ]);
+        let params = to_params(query);
+        assert_eq!(params["allowPartial"], Value::Bool(true));
+        assert_eq!(params["limit"], Value::from(10u64));
+        assert_eq!(params["cursor"], Value::from("abc"));
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert!(matches!(parse_version(None), Ok(RecordVersion::V2)));
+        assert!(matches!(
+            parse_version(Some(Value::from(1))),
+            Ok(RecordVersion::V1)
+        ));
+        assert!(matches!(
+            parse_version(Some(Value::from(2))),
+            Ok(RecordVersion::V2)
+        ));
+        let error = parse_version(Some(Value::from(3))).unwrap_err();
+        assert!(matches!(error.ty, ErrorType::InvalidRecordVersion));
+        let error = parse_version(Some(Value::from("v1"))).unwrap_err();
+        assert!(matches!(error.ty, ErrorType::InvalidParameters));
+    }
+
+    #[test]
+    fn test_is_not_modified() {
+        let request = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"a\", W/\"b\""))
+            .to_http_request();
+        assert!(is_not_modified(&request, "\"a\""));
+        assert!(is_not_modified(&request, "\"b\""));
+        assert!(!is_not_modified(&request, "\"c\""));
+    }
+
+    #[test]
+    fn test_respond() {
+        let request = actix_web::test::TestRequest::default().to_http_request();
+        let response = respond(&request, json!({ "a": 1 }), 30).unwrap();
+        let headers = response.headers();
+        assert!(headers
+            .get(header::CACHE_CONTROL)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with("private"));
+        assert_eq!(headers.get(header::VARY).unwrap(), VARY);
+
+        let etag = headers.get(header::ETAG).unwrap().clone();
+        let request = actix_web::test::TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, etag))
+            .to_http_request();
+        let response = respond(&request, json!({ "a": 1 }), 30).unwrap();
+        assert_eq!(response.status(), actix_web::http::StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers().get(header::VARY).unwrap(), VARY);
+    }
+}
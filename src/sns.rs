@@ -1,7 +1,4 @@
-use std::{
-    fmt::{Debug, Display},
-    ops::Deref,
-};
+use std::{fmt::Display, ops::Deref, sync::Arc};
 
 use actix_web::{
     http::header::{HeaderValue, CONTENT_TYPE},
@@ -9,23 +6,52 @@ use actix_web::{
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
+use sns_sdk::{
+    derivation::{derive, get_prefix, Domain},
+    record::{get_record_class, Record, RecordVersion},
+};
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
 
-use crate::{db::DbConnector, matrix::get_matrix_client, trace, ErrorType};
+use crate::{
+    cache::get_cached_rpc_client, db::DbConnector, matrix::get_matrix_client,
+    provisioning::ProvisioningRequest, trace, ErrorType,
+};
 
+pub mod context;
+pub mod domain_name;
+pub mod favourite;
+pub mod find_domains_by_record;
 pub mod get_all_domains_for_owner;
+pub mod get_all_records;
+pub mod get_create_record_v2_transaction;
+pub mod get_create_subdomain_transaction;
+pub mod get_delete_record_v2_transaction;
 pub mod get_domain_data;
 pub mod get_domain_data_v2;
+pub mod get_domain_history;
 pub mod get_domain_key;
+pub mod get_domain_price;
 pub mod get_domain_record_key;
 pub mod get_domain_record_v2_key;
 pub mod get_domain_reverse_key;
 pub mod get_favourite_domain;
+pub mod get_primary_domains;
 pub mod get_registration_transaction;
+pub mod get_set_favourite_domain_transaction;
 pub mod get_subdomains;
 pub mod get_supported_records;
+pub mod get_transfer_transaction;
+pub mod get_update_record_v2_transaction;
+pub mod get_validate_record_v2_transaction;
+pub mod name_service;
+pub mod params;
+pub mod pricing;
+pub mod record_v2_transaction;
 pub mod resolve_domain;
 pub mod reverse_lookup;
+pub mod tokenization;
+pub mod transaction;
 
 #[derive(Deserialize)]
 #[cfg_attr(test, derive(Serialize))]
@@ -56,10 +82,54 @@ pub enum Method {
     GetDomainData,
     #[serde(rename = "sns_getDomainDataV2")]
     GetDomainDataV2,
+    #[serde(rename = "sns_getAllRecords")]
+    GetAllRecords,
+    #[serde(rename = "sns_getPrimaryDomains")]
+    GetPrimaryDomains,
+    #[serde(rename = "sns_getDomainPrice")]
+    GetDomainPrice,
+    #[serde(rename = "sns_getCreateRecordV2Transaction")]
+    GetCreateRecordV2Transaction,
+    #[serde(rename = "sns_getUpdateRecordV2Transaction")]
+    GetUpdateRecordV2Transaction,
+    #[serde(rename = "sns_getDeleteRecordV2Transaction")]
+    GetDeleteRecordV2Transaction,
+    #[serde(rename = "sns_getValidateRecordV2Transaction")]
+    GetValidateRecordV2Transaction,
+    #[serde(rename = "sns_getTransferTransaction")]
+    GetTransferTransaction,
+    #[serde(rename = "sns_getSetFavouriteDomainTransaction")]
+    GetSetFavouriteDomainTransaction,
+    #[serde(rename = "sns_getCreateSubdomainTransaction")]
+    GetCreateSubdomainTransaction,
+    #[serde(rename = "sns_getDomainHistory")]
+    GetDomainHistory,
+    #[serde(rename = "sns_findDomainsByRecord")]
+    FindDomainsByRecord,
     #[serde(other)]
     Unsupported,
 }
 
+impl Method {
+    pub fn is_read(&self) -> bool {
+        matches!(
+            self,
+            Method::ResolveDomain
+                | Method::GetAllDomainsForOwner
+                | Method::GetFavouriteDomain
+                | Method::ReverseLookup
+                | Method::GetSubdomains
+                | Method::GetDomainData
+                | Method::GetDomainDataV2
+                | Method::GetAllRecords
+                | Method::GetPrimaryDomains
+                | Method::GetDomainPrice
+                | Method::GetDomainHistory
+                | Method::FindDomainsByRecord
+        )
+    }
+}
+
 #[derive(Deserialize)]
 #[cfg_attr(test, derive(Serialize))]
 pub struct RpcMessage {
@@ -88,6 +158,8 @@ pub struct RpcResponseError {
 pub struct RpcError {
     code: i64,
     message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    data: Option<Value>,
 }
 
 pub const JSON_RPC: &str = "2.0";
@@ -101,6 +173,34 @@ impl RpcMessage {
     }
 }
 
+impl RpcResponseError {
+    pub fn new(id: Value, error: &crate::Error) -> Self {
+        let error_code = match error.ty {
+            ErrorType::InvalidAuthentication | ErrorType::ProvisioningRecordNotFound => {
+                JsonRpcError::InvalidRequest
+            }
+            ErrorType::UnsupportedEndpoint => JsonRpcError::MethodNotFound,
+            ErrorType::MalformedRequest
+            | ErrorType::InvalidParameters
+            | ErrorType::MissingParameters => JsonRpcError::InvalidParams,
+            ErrorType::MinContextSlotNotReached => JsonRpcError::MinContextSlotNotReached,
+            ErrorType::SubscriptionLimitReached | ErrorType::ConnectionLimitReached => {
+                JsonRpcError::LimitExceeded
+            }
+            _ => JsonRpcError::ServerError,
+        };
+        Self {
+            error: RpcError {
+                code: error_code as i64,
+                message: format!("{error}"),
+                data: error.data.clone(),
+            },
+            jsonrpc: JSON_RPC,
+            id,
+        }
+    }
+}
+
 #[repr(i64)]
 pub enum JsonRpcError {
     ParseError = -32700,
@@ -109,6 +209,8 @@ pub enum JsonRpcError {
     InvalidParams = -32602,
     InternalError = -32603,
     ServerError = -32000,
+    LimitExceeded = -32005,
+    MinContextSlotNotReached = -32016,
 }
 
 #[derive(Debug)]
@@ -132,25 +234,7 @@ impl ResponseError for RpcErrorWrapper {
     }
 
     fn error_response(&self) -> actix_web::HttpResponse<actix_web::body::BoxBody> {
-        let error_code = match self.1.ty {
-            ErrorType::InvalidAuthentication | ErrorType::ProvisioningRecordNotFound => {
-                JsonRpcError::InvalidRequest
-            }
-            ErrorType::UnsupportedEndpoint => JsonRpcError::MethodNotFound,
-            ErrorType::MalformedRequest | ErrorType::InvalidParameters => {
-                JsonRpcError::InvalidParams
-            }
-            _ => JsonRpcError::ServerError,
-        };
-        let message = format!("{}", self.1);
-        let body = RpcResponseError {
-            error: RpcError {
-                code: error_code as i64,
-                message,
-            },
-            jsonrpc: JSON_RPC,
-            id: self.0.clone(),
-        };
+        let body = RpcResponseError::new(self.0.clone(), &self.1);
 
         if !self.status_code().is_client_error() {
             let matrix_client = get_matrix_client();
@@ -178,9 +262,22 @@ pub async fn route(
     let RpcMessage {
         params, id, method, ..
     } = message.into_inner();
-    let rpc_client = get_rpc_client(&db, &request)
+    let (params, read_config) = if method.is_read() {
+        context::split_read_config(params).map_err(|e| (id.clone(), e))?
+    } else {
+        (params, None)
+    };
+    let endpoint_url = get_provisioning_info(&db, &request)
         .await
-        .map_err(|e| (id.clone(), e))?;
+        .map_err(|e| (id.clone(), e))?
+        .http_url;
+    let tracker = Arc::new(context::ContextTracker::default());
+    let rpc_client = match &read_config {
+        Some(config) => {
+            context::get_context_rpc_client(endpoint_url.clone(), config, tracker.clone())
+        }
+        None => get_cached_rpc_client(endpoint_url.clone()),
+    };
 
     let result = match method {
         Method::ResolveDomain => resolve_domain::process(rpc_client, params).await,
@@ -200,11 +297,46 @@ pub async fn route(
         }
         Method::GetDomainData => get_domain_data::process(rpc_client, params).await,
         Method::GetDomainDataV2 => get_domain_data_v2::process(rpc_client, params).await,
+        Method::GetAllRecords => get_all_records::process(rpc_client, params).await,
+        Method::GetPrimaryDomains => get_primary_domains::process(rpc_client, params).await,
+        Method::GetDomainPrice => get_domain_price::process(rpc_client, params).await,
+        Method::GetCreateRecordV2Transaction => {
+            get_create_record_v2_transaction::process(rpc_client, params).await
+        }
+        Method::GetUpdateRecordV2Transaction => {
+            get_update_record_v2_transaction::process(rpc_client, params).await
+        }
+        Method::GetDeleteRecordV2Transaction => {
+            get_delete_record_v2_transaction::process(rpc_client, params).await
+        }
+        Method::GetValidateRecordV2Transaction => {
+            get_validate_record_v2_transaction::process(rpc_client, params).await
+        }
+        Method::GetTransferTransaction => {
+            get_transfer_transaction::process(rpc_client, params).await
+        }
+        Method::GetSetFavouriteDomainTransaction => {
+            get_set_favourite_domain_transaction::process(rpc_client, params).await
+        }
+        Method::GetCreateSubdomainTransaction => {
+            get_create_subdomain_transaction::process(rpc_client, params).await
+        }
+        Method::GetDomainHistory => get_domain_history::process(rpc_client, params).await,
+        Method::FindDomainsByRecord => find_domains_by_record::process(rpc_client, params).await,
         Method::Unsupported => {
             return Err((id.clone(), trace!(crate::ErrorType::UnsupportedEndpoint)).into())
         }
     }
-    .map_err(|e| (id.clone(), e))?;
+    .map_err(|e| (id.clone(), tracker.map_error(e)))?;
+    let result = match &read_config {
+        Some(config) => {
+            let slot = context::get_context_slot(endpoint_url, config, tracker)
+                .await
+                .map_err(|e| (id.clone(), e))?;
+            context::with_context(slot, result).map_err(|e| (id.clone(), e))?
+        }
+        None => result,
+    };
     Ok(web::Json(RpcResponseOk {
         jsonrpc: JSON_RPC,
         result,
@@ -215,7 +347,21 @@ pub async fn route(
 pub async fn get_rpc_client(
     db: &DbConnector,
     request: &HttpRequest,
+    commitment: Option<CommitmentConfig>,
 ) -> Result<RpcClient, crate::Error> {
+    let provisioning_info = get_provisioning_info(db, request).await?;
+    let endpoint_url = provisioning_info.http_url;
+    let rpc_client = match commitment {
+        Some(commitment) => RpcClient::new_with_commitment(endpoint_url, commitment),
+        None => get_cached_rpc_client(endpoint_url),
+    };
+    Ok(rpc_client)
+}
+
+pub async fn get_provisioning_info(
+    db: &DbConnector,
+    request: &HttpRequest,
+) -> Result<ProvisioningRequest, crate::Error> {
     let quicknode_id = request
         .headers()
         .get("x-quicknode-id")
@@ -228,52 +374,35 @@ pub async fn get_rpc_client(
         .ok_or(trace!(crate::ErrorType::InvalidAuthentication))?
         .to_str()
         .map_err(|e| trace!(crate::ErrorType::MalformedRequest, e))?;
-    let provisioning_info = db
-        .get_provisioning_request(quicknode_id, endpoint_id)
-        .await?;
-    let endpoint_url = provisioning_info.http_url;
-    let rpc_client = RpcClient::new(endpoint_url);
-    Ok(rpc_client)
+    db.get_provisioning_request(quicknode_id, endpoint_id).await
 }
 
-fn get_string_from_value_array(array: &[Value], index: usize) -> Result<String, crate::Error> {
-    let res = array
-        .get(index)
-        .ok_or(trace!(ErrorType::MissingParameters))?
-        .as_str()
-        .ok_or(trace!(ErrorType::InvalidParameters))?
-        .to_owned();
-    Ok(res)
+/// Record key derived from the key of the domain, which unlike
+/// `sns_sdk::record::get_record_key` also covers nested subdomains
+pub fn get_record_key_from_domain_key(
+    domain_key: &Pubkey,
+    record: Record,
+    version: RecordVersion,
+) -> Pubkey {
+    let name = get_prefix(Domain::Record(version)) + record.as_str();
+    derive(&name, domain_key, get_record_class(version))
 }
 
-fn get_opt_string_from_value_array(
-    array: &[Value],
-    index: usize,
-) -> Result<Option<String>, crate::Error> {
-    let res = array
-        .get(index)
-        .filter(|n| !n.is_null())
-        .map(|v| v.as_str().ok_or(trace!(ErrorType::InvalidParameters)))
-        .transpose()?
-        .map(|v| v.to_owned());
-    Ok(res)
-}
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
 
-fn get_int_from_value_array<T: TryFrom<u64>>(
-    array: &[Value],
-    index: usize,
-) -> Result<T, crate::Error>
-where
-    <T as TryFrom<u64>>::Error: Debug,
-{
-    let res = array
-        .get(index)
-        .ok_or(trace!(ErrorType::MissingParameters))?
-        .as_u64()
-        .ok_or(trace!(ErrorType::InvalidParameters))?
-        .try_into()
-        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
-    Ok(res)
+pub async fn get_multiple_accounts_batched(
+    rpc_client: &RpcClient,
+    keys: &[Pubkey],
+) -> Result<Vec<Option<Account>>, crate::Error> {
+    let mut result = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(MAX_MULTIPLE_ACCOUNTS) {
+        let accounts = rpc_client
+            .get_multiple_accounts(chunk)
+            .await
+            .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+        result.extend(accounts);
+    }
+    Ok(result)
 }
 
 #[test]
@@ -0,0 +1,316 @@
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use solana_client::{
+    client_error::{ClientErrorKind, Result as ClientResult},
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::RpcClientConfig,
+    rpc_custom_error::JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+    rpc_request::{RpcError, RpcRequest},
+    rpc_sender::{RpcSender, RpcTransportStats},
+};
+use solana_rpc_client::http_sender::HttpSender;
+use solana_sdk::commitment_config::{CommitmentConfig, CommitmentLevel};
+
+use crate::{append_trace, trace, ErrorType};
+
+use super::params::parse_params;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct ReadConfig {
+    pub commitment: Option<CommitmentLevel>,
+    pub min_context_slot: Option<u64>,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct ContextResponse {
+    pub context: Context,
+    pub value: Value,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct Context {
+    pub slot: u64,
+}
+
+impl ReadConfig {
+    const FIELDS: &'static [&'static str] = &["commitment", "minContextSlot"];
+
+    pub fn commitment_config(&self) -> Option<CommitmentConfig> {
+        self.commitment
+            .map(|commitment| CommitmentConfig { commitment })
+    }
+}
+
+/// Splits the read config from the method parameters. The config is passed as a trailing object
+/// for positional parameters, or under the `config` key for named parameters.
+pub fn split_read_config(params: Value) -> Result<(Value, Option<ReadConfig>), crate::Error> {
+    let (params, config) = match params {
+        Value::Array(mut array) => {
+            let config = match array.last() {
+                Some(Value::Object(_)) => array.pop(),
+                _ => None,
+            };
+            (Value::Array(array), config)
+        }
+        Value::Object(mut object) => {
+            let config = object.remove("config");
+            (Value::Object(object), config)
+        }
+        params => (params, None),
+    };
+    let config = config
+        .map(|c| parse_params(c, ReadConfig::FIELDS))
+        .transpose()
+        .map_err(|e| append_trace!(e))?;
+    Ok((params, config))
+}
+
+/// Slots observed by the reads of a request made with a read config
+#[derive(Default)]
+pub struct ContextTracker {
+    state: Mutex<TrackerState>,
+}
+
+#[derive(Default)]
+struct TrackerState {
+    slot: Option<u64>,
+    min_context_slot_not_reached: bool,
+}
+
+impl ContextTracker {
+    /// Lowest context slot returned by the reads, all of them reflect at least this slot
+    pub fn slot(&self) -> Option<u64> {
+        self.state.lock().unwrap().slot
+    }
+
+    /// Replaces the error of a request for which a read did not reach `minContextSlot`
+    pub fn map_error(&self, error: crate::Error) -> crate::Error {
+        if self.state.lock().unwrap().min_context_slot_not_reached {
+            trace!(ErrorType::MinContextSlotNotReached, error)
+        } else {
+            error
+        }
+    }
+
+    fn record(&self, response: &ClientResult<Value>) {
+        let mut state = self.state.lock().unwrap();
+        match response {
+            Ok(value) => {
+                let slot = value
+                    .get("context")
+                    .and_then(|c| c.get("slot"))
+                    .and_then(Value::as_u64);
+                if let Some(slot) = slot {
+                    state.slot = Some(state.slot.map_or(slot, |s| s.min(slot)));
+                }
+            }
+            Err(e) => {
+                if let ClientErrorKind::RpcError(RpcError::RpcResponseError { code, .. }) = e.kind()
+                {
+                    if *code == JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED {
+                        state.min_context_slot_not_reached = true;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Transport adding `minContextSlot` to the config of every read and recording the context slot
+/// of the responses
+struct ContextSender {
+    inner: HttpSender,
+    min_context_slot: Option<u64>,
+    tracker: Arc<ContextTracker>,
+}
+
+#[async_trait]
+impl RpcSender for ContextSender {
+    async fn send(&self, request: RpcRequest, mut params: Value) -> ClientResult<Value> {
+        set_read_config(request, &mut params, self.min_context_slot);
+        let response = self.inner.send(request, params).await;
+        self.tracker.record(&response);
+        response
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+// Position of the config object in the parameters of the reads accepting `minContextSlot`
+fn config_index(request: RpcRequest) -> Option<usize> {
+    match request {
+        RpcRequest::GetSlot => Some(0),
+        RpcRequest::GetAccountInfo
+        | RpcRequest::GetBalance
+        | RpcRequest::GetMultipleAccounts
+        | RpcRequest::GetProgramAccounts
+        | RpcRequest::GetSignaturesForAddress => Some(1),
+        RpcRequest::GetTokenAccountsByOwner => Some(2),
+        _ => None,
+    }
+}
+
+fn set_read_config(request: RpcRequest, params: &mut Value, min_context_slot: Option<u64>) {
+    let (Some(index), Value::Array(params)) = (config_index(request), params) else {
+        return;
+    };
+    if params.len() <= index {
+        params.resize(index + 1, Value::Null);
+    }
+    if params[index].is_null() {
+        params[index] = Value::Object(Map::new());
+    }
+    let Value::Object(config) = &mut params[index] else {
+        return;
+    };
+    if let Some(slot) = min_context_slot {
+        config.insert("minContextSlot".to_owned(), slot.into());
+    }
+    // Program accounts only carry a context when requested, the client parses both forms
+    if request == RpcRequest::GetProgramAccounts {
+        config.insert("withContext".to_owned(), true.into());
+    }
+}
+
+/// Builds a client whose reads all use the commitment and `minContextSlot` of the config
+pub fn get_context_rpc_client(
+    endpoint_url: String,
+    config: &ReadConfig,
+    tracker: Arc<ContextTracker>,
+) -> RpcClient {
+    let sender = ContextSender {
+        inner: HttpSender::new(endpoint_url),
+        min_context_slot: config.min_context_slot,
+        tracker,
+    };
+    let commitment = config.commitment_config().unwrap_or_default();
+    RpcClient::new_sender(sender, RpcClientConfig::with_commitment(commitment))
+}
+
+/// Slot of the context of a response. Methods that did not read any account with a context
+/// report the current slot, also subject to `minContextSlot`.
+pub async fn get_context_slot(
+    endpoint_url: String,
+    config: &ReadConfig,
+    tracker: Arc<ContextTracker>,
+) -> Result<u64, crate::Error> {
+    if let Some(slot) = tracker.slot() {
+        return Ok(slot);
+    }
+    let rpc_client = get_context_rpc_client(endpoint_url, config, tracker.clone());
+    rpc_client
+        .get_slot()
+        .await
+        .map_err(|e| tracker.map_error(trace!(ErrorType::SolanaRpcError, e)))
+}
+
+pub fn with_context(slot: u64, value: Value) -> Result<Value, crate::Error> {
+    let response = ContextResponse {
+        context: Context { slot },
+        value,
+    };
+    serde_json::to_value(response).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_read_config() {
+        let params = serde_json::json!(["bonfida.sol", { "commitment": "confirmed" }]);
+        let (params, config) = split_read_config(params).unwrap();
+        assert_eq!(params, serde_json::json!(["bonfida.sol"]));
+        let config = config.unwrap();
+        assert_eq!(
+            config.commitment_config(),
+            Some(CommitmentConfig::confirmed())
+        );
+
+        let params = serde_json::json!({
+            "domain": "bonfida.sol",
+            "config": { "min_context_slot": 42 },
+        });
+        let (params, config) = split_read_config(params).unwrap();
+        assert_eq!(params, serde_json::json!({ "domain": "bonfida.sol" }));
+        assert_eq!(config.unwrap().min_context_slot, Some(42));
+
+        let (_, config) = split_read_config(serde_json::json!(["bonfida.sol"])).unwrap();
+        assert!(config.is_none());
+
+        let params = serde_json::json!(["bonfida.sol", { "commitment": "unknown" }]);
+        assert!(split_read_config(params).is_err());
+    }
+
+    #[test]
+    fn test_set_read_config() {
+        let key = "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb";
+        let mut params = serde_json::json!([key, { "encoding": "base64" }]);
+        set_read_config(RpcRequest::GetAccountInfo, &mut params, Some(42));
+        assert_eq!(
+            params,
+            serde_json::json!([key, { "encoding": "base64", "minContextSlot": 42 }])
+        );
+
+        let mut params = serde_json::json!([key, { "filters": [] }]);
+        set_read_config(RpcRequest::GetProgramAccounts, &mut params, None);
+        assert_eq!(
+            params,
+            serde_json::json!([key, { "filters": [], "withContext": true }])
+        );
+
+        let mut params = serde_json::json!([key, { "mint": key }]);
+        set_read_config(RpcRequest::GetTokenAccountsByOwner, &mut params, Some(42));
+        assert_eq!(
+            params,
+            serde_json::json!([key, { "mint": key }, { "minContextSlot": 42 }])
+        );
+
+        let mut params = serde_json::json!([]);
+        set_read_config(
+            RpcRequest::GetMinimumBalanceForRentExemption,
+            &mut params,
+            Some(42),
+        );
+        assert_eq!(params, serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_context_tracker() {
+        use solana_client::client_error::ClientError;
+        use solana_client::rpc_request::RpcResponseErrorData;
+
+        let tracker = ContextTracker::default();
+        assert_eq!(tracker.slot(), None);
+        tracker.record(&Ok(
+            serde_json::json!({ "context": { "slot": 12 }, "value": null }),
+        ));
+        tracker.record(&Ok(
+            serde_json::json!({ "context": { "slot": 10 }, "value": null }),
+        ));
+        tracker.record(&Ok(serde_json::json!(14)));
+        assert_eq!(tracker.slot(), Some(10));
+        let error = tracker.map_error(trace!(ErrorType::SolanaRpcError));
+        assert!(matches!(error.ty, ErrorType::SolanaRpcError));
+
+        tracker.record(&Err(ClientError::from(RpcError::RpcResponseError {
+            code: JSON_RPC_SERVER_ERROR_MIN_CONTEXT_SLOT_NOT_REACHED,
+            message: "Minimum context slot has not been reached".to_owned(),
+            data: RpcResponseErrorData::Empty,
+        })));
+        let error = tracker.map_error(trace!(ErrorType::SolanaRpcError));
+        assert!(matches!(error.ty, ErrorType::MinContextSlotNotReached));
+    }
+}
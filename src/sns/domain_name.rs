@@ -0,0 +1,179 @@
+use std::{fmt::Display, ops::Deref};
+
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::{trace, ErrorType};
+
+const PUNYCODE_PREFIX: &str = "xn--";
+const ZERO_WIDTH_JOINER: char = '\u{200D}';
+
+/// A domain name normalized to the form used for key derivation: lowercase, punycode labels
+/// decoded, in NFC and without the trailing dot or `.sol` suffix. The zero width joiner is kept
+/// since it is part of emoji sequences.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct DomainName(String);
+
+impl DomainName {
+    pub fn new(domain: &str) -> Result<Self, String> {
+        let domain = domain.trim();
+        let domain = domain.strip_suffix('.').unwrap_or(domain);
+        let domain = domain.to_lowercase();
+        let domain = domain.strip_suffix(".sol").unwrap_or(&domain);
+        if domain.is_empty() {
+            return Err("empty domain name".to_owned());
+        }
+        let labels = domain
+            .split('.')
+            .map(normalize_label)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(labels.join(".")))
+    }
+
+    /// A single label, such as the label of a subdomain to create. Unlike `new` the `.sol`
+    /// suffix is not stripped, any dot is rejected.
+    pub fn label(label: &str) -> Result<Self, String> {
+        let label = label.trim().to_lowercase();
+        if label.contains('.') {
+            return Err(format!("{label} is not a single label"));
+        }
+        normalize_label(&label).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Response of the methods taking a domain, which also return the normalized name the result was
+/// derived from
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct NormalizedResponse {
+    pub domain: String,
+    pub value: Value,
+}
+
+pub fn with_normalized_domain(domain: &str, value: Value) -> Result<Value, crate::Error> {
+    let response = NormalizedResponse {
+        domain: domain.to_owned(),
+        value,
+    };
+    serde_json::to_value(response).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+fn normalize_label(label: &str) -> Result<String, String> {
+    let label = match label.strip_prefix(PUNYCODE_PREFIX) {
+        Some(encoded) => idna::punycode::decode_to_string(encoded)
+            .ok_or_else(|| format!("invalid punycode label {label}"))?
+            .to_lowercase(),
+        None => label.to_owned(),
+    };
+    let label = label.nfc().collect::<String>();
+    if label.is_empty() {
+        return Err("empty label".to_owned());
+    }
+    if let Some(c) = label.chars().find(|c| is_invisible(*c)) {
+        return Err(format!("invisible character U+{:04X}", c as u32));
+    }
+    Ok(label)
+}
+
+fn is_invisible(c: char) -> bool {
+    if c == ZERO_WIDTH_JOINER {
+        return false;
+    }
+    c.is_whitespace()
+        || c.is_control()
+        || matches!(
+            c,
+            '\u{00AD}'
+                | '\u{034F}'
+                | '\u{061C}'
+                | '\u{115F}'
+                | '\u{1160}'
+                | '\u{17B4}'
+                | '\u{17B5}'
+                | '\u{180B}'..='\u{180F}'
+                | '\u{200B}'..='\u{200F}'
+                | '\u{202A}'..='\u{202E}'
+                | '\u{2060}'..='\u{206F}'
+                | '\u{3164}'
+                | '\u{FEFF}'
+                | '\u{FFA0}'
+                | '\u{FFF0}'..='\u{FFF8}'
+        )
+}
+
+impl Deref for DomainName {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl Display for DomainName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for DomainName {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let domain = String::deserialize(deserializer)?;
+        Self::new(&domain).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        for domain in ["bonfida", "Bonfida.SOL", "bonfida.sol.", " bonfida "] {
+            assert_eq!(DomainName::new(domain).unwrap().as_str(), "bonfida");
+        }
+        assert_eq!(
+            DomainName::new("dex.bonfida.sol").unwrap().as_str(),
+            "dex.bonfida"
+        );
+        assert_eq!(DomainName::new("cafe\u{301}").unwrap().as_str(), "café");
+        assert_eq!(DomainName::label("Cafe\u{301}").unwrap().as_str(), "café");
+        assert_eq!(DomainName::new("xn--caf-dma.sol").unwrap().as_str(), "café");
+        assert_eq!(DomainName::new("👨‍🌾").unwrap().as_str(), "👨‍🌾");
+        assert_eq!(DomainName::new("xn--bp8h").unwrap().as_str(), "🐶");
+    }
+
+    #[test]
+    fn test_rejections() {
+        assert!(DomainName::new("").is_err());
+        assert!(DomainName::new(".sol").is_err());
+        assert!(DomainName::new("dex..bonfida").is_err());
+        assert!(DomainName::new("bon\u{200B}fida").is_err());
+        assert!(DomainName::new("bon\u{FEFF}fida").is_err());
+        assert!(DomainName::new("bon fida").is_err());
+        assert!(DomainName::new("xn--").is_err());
+    }
+
+    #[test]
+    fn test_with_normalized_domain() {
+        let domain = DomainName::new("Cafe\u{301}.sol").unwrap();
+        let value = with_normalized_domain(&domain, Value::from("key")).unwrap();
+        let response: NormalizedResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(response.domain, "café");
+        assert_eq!(response.value, "key");
+    }
+
+    #[test]
+    fn test_label() {
+        assert_eq!(DomainName::label(" Dex ").unwrap().as_str(), "dex");
+        assert_eq!(DomainName::label("xn--bp8h").unwrap().as_str(), "🐶");
+        assert!(DomainName::label("dex.sol").is_err());
+        assert!(DomainName::label("dex.bonfida").is_err());
+        assert!(DomainName::label("").is_err());
+        assert!(DomainName::label("d\u{200B}ex").is_err());
+    }
+}
@@ -0,0 +1,70 @@
+use sns_sdk::{
+    favourite_domain::{
+        derive_favourite_domain_key, get_register_favourite_instruction, register_favourite,
+    },
+    NAME_OFFERS_PROGRAM_ID,
+};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program,
+};
+
+// `sns_sdk::favourite_domain::get_register_favourite_instruction` does not take the parent
+// account that subdomains require, it is appended to the accounts of the SDK instruction
+pub fn register_favourite(
+    name_account: &Pubkey,
+    owner: &Pubkey,
+    parent_name_account: Option<&Pubkey>,
+) -> Instruction {
+    let mut instruction = get_register_favourite_instruction(
+        NAME_OFFERS_PROGRAM_ID,
+        register_favourite::Accounts {
+            name: name_account,
+            favourite_domain: &derive_favourite_domain_key(owner),
+            owner,
+            system_program: &system_program::ID,
+        },
+        register_favourite::Params {},
+    );
+    if let Some(parent) = parent_name_account {
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(*parent, false));
+    }
+    instruction
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_favourite() {
+        let name_account = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let parent = Pubkey::new_unique();
+        let sdk_instruction = get_register_favourite_instruction(
+            NAME_OFFERS_PROGRAM_ID,
+            register_favourite::Accounts {
+                name: &name_account,
+                favourite_domain: &derive_favourite_domain_key(&owner),
+                owner: &owner,
+                system_program: &system_program::ID,
+            },
+            register_favourite::Params {},
+        );
+
+        let instruction = register_favourite(&name_account, &owner, None);
+        assert_eq!(instruction, sdk_instruction);
+
+        let instruction = register_favourite(&name_account, &owner, Some(&parent));
+        assert_eq!(instruction.program_id, sdk_instruction.program_id);
+        assert_eq!(instruction.data, sdk_instruction.data);
+        assert_eq!(instruction.accounts[..4], sdk_instruction.accounts[..]);
+        assert_eq!(
+            instruction.accounts[4],
+            AccountMeta::new_readonly(parent, false)
+        );
+    }
+}
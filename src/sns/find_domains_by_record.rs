@@ -0,0 +1,428 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sns_records::state::{
+    record_header::RecordHeader,
+    validation::{get_validation_length, Validation},
+};
+use sns_sdk::{
+    non_blocking::resolve,
+    record::{
+        record_v1::check_sol_record, record_v2::serialize_record_v2_content, Record, RecordVersion,
+        CENTRAL_STATE_RECORD_V2,
+    },
+};
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
+
+use super::{
+    get_domain_data::{parse_record_v2, QueryResult, ValidationIdFormat},
+    get_multiple_accounts_batched, get_record_key_from_domain_key,
+    params::parse_params,
+    tokenization::get_domain_owners,
+};
+
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+
+// NameRecordHeader layout: parent_name (32) | owner (32) | class (32)
+const CLASS_OFFSET: usize = 64;
+
+const STALENESS_VALIDATIONS: [Validation; 2] = [Validation::None, Validation::Solana];
+const ROA_VALIDATIONS: [Validation; 5] = [
+    Validation::None,
+    Validation::Solana,
+    Validation::Ethereum,
+    Validation::UnverifiedSolana,
+    Validation::XChain,
+];
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    record: String,
+    value: String,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Version {
+    V1,
+    V2,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultItem {
+    domain: Option<String>,
+    domain_key: String,
+    record_key: String,
+    version: Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation: Option<RecordValidation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_signature_valid: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultPage {
+    domains: Vec<ResultItem>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordValidation {
+    staleness_validation: String,
+    roa_validation: String,
+    is_stale: bool,
+    is_roa_valid: bool,
+}
+
+struct Match {
+    domain_key: Pubkey,
+    record_key: Pubkey,
+    version: Version,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["record", "value", "limit", "cursor"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+// The record accounts are found with 11 getProgramAccounts scans of the name service program, one
+// for V1 and one per V2 validation combination. V2 scans match an exact `dataSize`, while the V1
+// scan can only filter on the class and the content since V1 records are allocated with an
+// arbitrary size. The scans only return the parent key, full accounts, owners and names are
+// fetched for the requested page.
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(trace!(ErrorType::InvalidParameters, limit));
+    }
+    let record =
+        Record::try_from_str(&params.record).map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    let v1_content = serialize_record_v1_content(&params.value, record)?;
+    let v2_content = serialize_record_v2_content(&params.value, record)
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+
+    let mut queries = vec![(Version::V1, record_v1_filters(&v1_content))];
+    for filters in record_v2_filters(&v2_content) {
+        queries.push((Version::V2, filters));
+    }
+    let pages = stream::iter(queries)
+        .map(|(version, filters)| {
+            let rpc_client = &rpc_client;
+            async move {
+                let config = RpcProgramAccountsConfig {
+                    filters: Some(filters),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        data_slice: Some(UiDataSliceConfig {
+                            offset: 0,
+                            length: 32,
+                        }),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                };
+                let accounts = rpc_client
+                    .get_program_accounts_with_config(&spl_name_service::id(), config)
+                    .await
+                    .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+                Ok::<_, crate::Error>((version, accounts))
+            }
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut matches = vec![];
+    for (version, accounts) in pages {
+        for (record_key, account) in accounts {
+            let Some(domain_key) = account
+                .data
+                .get(..32)
+                .and_then(|b| Pubkey::try_from(b).ok())
+            else {
+                continue;
+            };
+            // The memcmp filters match the content only, the key derivation confirms the record type
+            let expected_key = match version {
+                Version::V1 => {
+                    get_record_key_from_domain_key(&domain_key, record, RecordVersion::V1)
+                }
+                Version::V2 => {
+                    get_record_key_from_domain_key(&domain_key, record, RecordVersion::V2)
+                }
+            };
+            if record_key != expected_key {
+                continue;
+            }
+            matches.push(Match {
+                domain_key,
+                record_key,
+                version,
+            });
+        }
+    }
+    matches.sort_by_cached_key(|m| m.record_key.to_string());
+
+    let start = match &params.cursor {
+        Some(cursor) => matches.partition_point(|m| m.record_key.to_string() <= *cursor),
+        None => 0,
+    };
+    let end = matches.len().min(start + limit);
+    let next_cursor = if end < matches.len() {
+        matches.get(end - 1).map(|m| m.record_key.to_string())
+    } else {
+        None
+    };
+    let matches = matches.drain(start..end).collect::<Vec<_>>();
+
+    let record_keys = matches.iter().map(|m| m.record_key).collect::<Vec<_>>();
+    let accounts = get_multiple_accounts_batched(&rpc_client, &record_keys)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let domain_keys = matches.iter().map(|m| m.domain_key).collect::<Vec<_>>();
+    let owners = get_domain_owners(&rpc_client, &domain_keys)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let names = resolve::resolve_reverse_batch(&rpc_client, &domain_keys)
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+
+    let mut domains = Vec::with_capacity(matches.len());
+    for (((m, account), owner), name) in matches.into_iter().zip(accounts).zip(owners).zip(names) {
+        // Records of deleted domains are left dangling
+        let (Some(account), Some(owner)) = (account, owner) else {
+            continue;
+        };
+        let (validation, is_signature_valid) = match m.version {
+            Version::V1 => {
+                if !is_exact_v1_match(&account.data, &v1_content, record) {
+                    continue;
+                }
+                let is_signature_valid = matches!(record, Record::Sol)
+                    .then(|| is_valid_sol_signature(&account.data, &m.record_key, &owner.owner));
+                (None, is_signature_valid)
+            }
+            Version::V2 => (parse_validation(&owner.owner, &account.data, record), None),
+        };
+        domains.push(ResultItem {
+            domain: name,
+            domain_key: m.domain_key.to_string(),
+            record_key: m.record_key.to_string(),
+            version: m.version,
+            validation,
+            is_signature_valid,
+        });
+    }
+    let page = ResultPage {
+        domains,
+        next_cursor,
+    };
+    serde_json::to_value(page).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+// V1 SOL records hold the address followed by the owner's signature of the hex encoded address and
+// record key
+fn is_valid_sol_signature(data: &[u8], record_key: &Pubkey, owner: &Pubkey) -> bool {
+    let content = &data[NameRecordHeader::LEN.min(data.len())..];
+    let (Some(address), Some(signature)) = (content.get(..32), content.get(32..96)) else {
+        return false;
+    };
+    let message = base16::encode_lower(&[address, &record_key.to_bytes()].concat());
+    check_sol_record(message.as_bytes(), signature, *owner).unwrap_or(false)
+}
+
+fn parse_validation(
+    domain_owner: &Pubkey,
+    data: &[u8],
+    record: Record,
+) -> Option<RecordValidation> {
+    match parse_record_v2(domain_owner, data, record, ValidationIdFormat::String) {
+        Ok(QueryResult::V2 {
+            staleness_validation,
+            roa_validation,
+            is_stale,
+            is_roa_valid,
+            ..
+        }) => Some(RecordValidation {
+            staleness_validation,
+            roa_validation,
+            is_stale,
+            is_roa_valid,
+        }),
+        Ok(QueryResult::V1(_)) => None,
+        Err(e) => {
+            log::warn!(
+                "Skipping validation of invalid {} record: {e:?}",
+                record.as_str()
+            );
+            None
+        }
+    }
+}
+
+fn serialize_record_v1_content(value: &str, record: Record) -> Result<Vec<u8>, crate::Error> {
+    let content = match record {
+        Record::Eth | Record::Bsc => {
+            let address = value.strip_prefix("0x").unwrap_or(value);
+            let bytes = base16::decode(address.as_bytes())
+                .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+            if bytes.len() != 20 {
+                return Err(trace!(ErrorType::InvalidParameters, value));
+            }
+            bytes
+        }
+        // V1 SOL records store the address followed by a signature
+        Record::Sol => Pubkey::from_str(value)
+            .map_err(|e| trace!(ErrorType::InvalidParameters, e))?
+            .to_bytes()
+            .to_vec(),
+        _ => value.as_bytes().to_vec(),
+    };
+    Ok(content)
+}
+
+fn is_exact_v1_match(data: &[u8], content: &[u8], record: Record) -> bool {
+    if matches!(record, Record::Sol) {
+        return true;
+    }
+    // Text records are zero padded, reject matches on a prefix of a longer value
+    data.get(NameRecordHeader::LEN + content.len()..)
+        .is_none_or(|rest| rest.iter().all(|b| *b == 0))
+}
+
+fn record_v1_filters(content: &[u8]) -> Vec<RpcFilterType> {
+    vec![
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            CLASS_OFFSET,
+            Pubkey::default().to_bytes().to_vec(),
+        )),
+        RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            NameRecordHeader::LEN,
+            content.to_vec(),
+        )),
+    ]
+}
+
+// The content offset depends on the validation ids stored before it, so each combination is
+// queried separately with an exact match on the record header and the account size
+fn record_v2_filters(content: &[u8]) -> Vec<Vec<RpcFilterType>> {
+    let class = CENTRAL_STATE_RECORD_V2.to_bytes().to_vec();
+    let mut result = vec![];
+    for staleness in STALENESS_VALIDATIONS {
+        for roa in ROA_VALIDATIONS {
+            let mut header = Vec::with_capacity(RecordHeader::LEN);
+            header.extend_from_slice(&(staleness as u16).to_le_bytes());
+            header.extend_from_slice(&(roa as u16).to_le_bytes());
+            header.extend_from_slice(&(content.len() as u32).to_le_bytes());
+            let content_offset = NameRecordHeader::LEN
+                + RecordHeader::LEN
+                + get_validation_length(staleness) as usize
+                + get_validation_length(roa) as usize;
+            result.push(vec![
+                RpcFilterType::DataSize((content_offset + content.len()) as u64),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(CLASS_OFFSET, class.clone())),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(NameRecordHeader::LEN, header)),
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(content_offset, content.to_vec())),
+            ]);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serialize_record_v1_content() {
+        let content =
+            serialize_record_v1_content("0x4bfbfd1e018f9f27eeb788160579daf7e2cd7da7", Record::Eth)
+                .unwrap();
+        assert_eq!(content.len(), 20);
+        assert_eq!(content[0], 0x4b);
+        assert!(serialize_record_v1_content("0x4bfb", Record::Eth).is_err());
+        assert_eq!(
+            serialize_record_v1_content("bonfida", Record::Twitter).unwrap(),
+            b"bonfida"
+        );
+    }
+
+    #[test]
+    fn test_is_exact_v1_match() {
+        let mut data = vec![0; NameRecordHeader::LEN];
+        data.extend_from_slice(b"bonfida");
+        data.extend_from_slice(&[0; 16]);
+        assert!(is_exact_v1_match(&data, b"bonfida", Record::Twitter));
+        assert!(!is_exact_v1_match(&data, b"bon", Record::Twitter));
+    }
+
+    #[test]
+    fn test_is_valid_sol_signature() {
+        use solana_sdk::signer::{keypair::Keypair, Signer};
+        let owner = Keypair::new();
+        let record_key = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let message = base16::encode_lower(&[address.as_ref(), record_key.as_ref()].concat());
+        let signature = owner.sign_message(message.as_bytes());
+        let mut data = vec![0; NameRecordHeader::LEN];
+        data.extend_from_slice(address.as_ref());
+        data.extend_from_slice(signature.as_ref());
+        assert!(is_valid_sol_signature(&data, &record_key, &owner.pubkey()));
+        assert!(!is_valid_sol_signature(
+            &data,
+            &Pubkey::new_unique(),
+            &owner.pubkey()
+        ));
+        assert!(!is_valid_sol_signature(
+            &data[..NameRecordHeader::LEN + 32],
+            &record_key,
+            &owner.pubkey()
+        ));
+    }
+
+    #[tokio::test]
+    async fn integrated_test_0() {
+        use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let client = reqwest::Client::new();
+        let message = RpcMessage {
+            jsonrpc: JSON_RPC.to_owned(),
+            method: Method::FindDomainsByRecord,
+            params: serde_json::to_value(["github", "https://github.com/Bonfida"]).unwrap(),
+            id: serde_json::to_value(42u8).unwrap(),
+        };
+        let post_request = client.post(&endpoint).json(&message).build().unwrap();
+        let response = client.execute(post_request).await.unwrap();
+        if response.status().is_success() {
+            let result: RpcResponseOk<String> = response.json().await.unwrap();
+            let page: ResultPage = serde_json::from_value(result.result).unwrap();
+            assert!(page.domains.iter().all(|i| !i.record_key.is_empty()));
+        } else {
+            let text = response.text().await.unwrap();
+            eprintln!("Error body:\n {text}");
+            panic!()
+        }
+    }
+}
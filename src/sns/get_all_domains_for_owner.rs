@@ -7,27 +7,51 @@ use sns_sdk::non_blocking::resolve;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-use super::get_string_from_value_array;
+use super::{params::parse_params, tokenization};
+
+const MAX_PAGE_SIZE: usize = 1_000;
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
     owner: String,
+    allow_partial: Option<bool>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+    sort: Option<SortOrder>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SortOrder {
+    Name,
+    Key,
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct ResultItem {
-    name: String,
+    name: Option<String>,
     key: String,
+    tokenized: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultPage {
+    domains: Vec<ResultItem>,
+    warnings: Vec<String>,
+    next_cursor: Option<String>,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["owner", "allowPartial", "limit", "cursor", "sort"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let owner = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { owner })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+
+    fn is_paginated(&self) -> bool {
+        self.limit.is_some() || self.cursor.is_some()
     }
 }
 
@@ -35,21 +59,120 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
     let params = Params::deserialize(params)?;
     let owner =
         Pubkey::from_str(&params.owner).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
-    let domain_keys = resolve::get_domains_owner(&rpc_client, owner)
+    let mut domain_keys = resolve::get_domains_owner(&rpc_client, owner)
         .await
         .map_err(|e| trace!(ErrorType::Generic, e))?
         .into_iter()
         .collect::<Vec<_>>();
-    let reversed = resolve::resolve_reverse_batch(&rpc_client, &domain_keys)
+    let direct_count = domain_keys.len();
+    let tokenized_keys = tokenization::get_tokenized_domains(&rpc_client, &owner)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    for key in tokenized_keys {
+        if !domain_keys.contains(&key) {
+            domain_keys.push(key);
+        }
+    }
+    let mut result = domain_keys
+        .into_iter()
+        .enumerate()
+        .map(|(i, key)| ResultItem {
+            name: None,
+            key: key.to_string(),
+            tokenized: i >= direct_count,
+        })
+        .collect::<Vec<_>>();
+    let allow_partial = params.allow_partial.unwrap_or_default();
+    let mut warnings = vec![];
+
+    if !params.is_paginated() {
+        resolve_names(&rpc_client, &mut result, allow_partial, &mut warnings).await?;
+        if let Some(sort) = params.sort {
+            sort_items(&mut result, sort);
+        }
+        let page = ResultPage {
+            domains: result,
+            warnings,
+            next_cursor: None,
+        };
+        return serde_json::to_value(page).map_err(|e| trace!(ErrorType::Generic, e));
+    }
+
+    let limit = params.limit.unwrap_or(MAX_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(trace!(ErrorType::InvalidParameters, limit));
+    }
+    // Sorting by key only resolves the reverse records of the requested page, sorting by name
+    // needs all of them
+    let sort = params.sort.unwrap_or(SortOrder::Key);
+    if sort == SortOrder::Name {
+        resolve_names(&rpc_client, &mut result, allow_partial, &mut warnings).await?;
+    }
+    sort_items(&mut result, sort);
+    let start = match &params.cursor {
+        Some(cursor) => {
+            result
+                .iter()
+                .position(|item| &item.key == cursor)
+                .ok_or(trace!(ErrorType::InvalidParameters, cursor))?
+                + 1
+        }
+        None => 0,
+    };
+    let end = result.len().min(start + limit);
+    let next_cursor = if end < result.len() {
+        result.get(end - 1).map(|item| item.key.clone())
+    } else {
+        None
+    };
+    let mut domains = result.drain(start..end).collect::<Vec<_>>();
+    if sort == SortOrder::Key {
+        resolve_names(&rpc_client, &mut domains, allow_partial, &mut warnings).await?;
+    }
+    let page = ResultPage {
+        domains,
+        warnings,
+        next_cursor,
+    };
+    serde_json::to_value(page).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+async fn resolve_names(
+    rpc_client: &RpcClient,
+    items: &mut [ResultItem],
+    allow_partial: bool,
+    warnings: &mut Vec<String>,
+) -> Result<(), crate::Error> {
+    let keys = items
+        .iter()
+        .map(|item| Pubkey::from_str(&item.key))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    let reversed = resolve::resolve_reverse_batch(rpc_client, &keys)
         .await
         .map_err(|e| trace!(ErrorType::Generic, e))?;
-    let mut result = Vec::with_capacity(domain_keys.len());
-    for (key, n) in domain_keys.into_iter().zip(reversed.into_iter()) {
-        let name = n.ok_or(trace!(ErrorType::ReverseRecordNotFound))?;
-        let key = key.to_string();
-        result.push(ResultItem { name, key });
+    for (item, name) in items.iter_mut().zip(reversed) {
+        if name.is_none() {
+            if !allow_partial {
+                return Err(trace!(ErrorType::ReverseRecordNotFound, item.key));
+            }
+            warnings.push(format!(
+                "Failed to find a reverse record for domain {}",
+                item.key
+            ));
+        }
+        item.name = name;
+    }
+    Ok(())
+}
+
+fn sort_items(items: &mut [ResultItem], sort: SortOrder) {
+    match sort {
+        SortOrder::Name => items.sort_by(|a, b| {
+            (a.name.is_none(), &a.name, &a.key).cmp(&(b.name.is_none(), &b.name, &b.key))
+        }),
+        SortOrder::Key => items.sort_by(|a, b| a.key.cmp(&b.key)),
     }
-    Ok(serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e)))?
 }
 
 #[tokio::test]
@@ -59,30 +182,88 @@ async fn test_0() {
     let params = serde_json::to_value(["HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA"]).unwrap();
     let rpc_client = RpcClient::new(endpoint);
     let result = process(rpc_client, params).await.unwrap();
-    let result: Vec<ResultItem> = serde_json::from_value(result).unwrap();
-    let value = result.into_iter().collect::<HashSet<_>>();
+    let result: ResultPage = serde_json::from_value(result).unwrap();
+    assert!(result.next_cursor.is_none());
+    let value = result.domains.into_iter().collect::<HashSet<_>>();
     assert_eq!(value.len(), 4);
     assert!(value.contains(&ResultItem {
         key: "9B8y69VYEvLuwnaPdqNWL2wrV2XCLKrNAewC3FQEXptn".to_owned(),
-        name: "👨‍🌾".to_owned()
+        name: Some("👨‍🌾".to_owned()),
+        tokenized: false,
     }));
     assert!(value.contains(&ResultItem {
         key: "BAW7NsKcY8SLr98ZNYcH2HeDvPBPE2EoyjuPKcJ9bW1d".to_owned(),
-        name: "9772".to_owned()
+        name: Some("9772".to_owned()),
+        tokenized: false,
     }));
     assert!(value.contains(&ResultItem {
         key: "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb".to_owned(),
-        name: "bonfida".to_owned()
+        name: Some("bonfida".to_owned()),
+        tokenized: false,
     }));
     assert!(value.contains(&ResultItem {
         key: "8xMJaFHqas1gzS7xLuWh298TDuBUw4hqLXL2ZFs376hH".to_owned(),
-        name: "springboks".to_owned()
+        name: Some("springboks".to_owned()),
+        tokenized: false,
     }));
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
+    #[test]
+    fn test_is_paginated() {
+        let owner = "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA";
+        let params = serde_json::json!({ "owner": owner, "allowPartial": false, "sort": "key" });
+        assert!(!Params::deserialize(params).unwrap().is_paginated());
+        let params = serde_json::json!({ "owner": owner, "limit": 10 });
+        assert!(Params::deserialize(params).unwrap().is_paginated());
+        let params = serde_json::json!({ "owner": owner, "cursor": owner });
+        assert!(Params::deserialize(params).unwrap().is_paginated());
+    }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let owner = "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA";
+        let params = serde_json::json!({ "owner": owner, "limit": 3, "sort": "name" });
+        let result = process(RpcClient::new(endpoint.clone()), params)
+            .await
+            .unwrap();
+        let first_page: ResultPage = serde_json::from_value(result).unwrap();
+        assert_eq!(first_page.domains.len(), 3);
+        assert!(first_page.next_cursor.is_some());
+        let names = first_page
+            .domains
+            .iter()
+            .map(|d| d.name.clone().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["9772", "bonfida", "springboks"]);
+
+        let params = serde_json::json!({
+            "owner": owner,
+            "limit": 3,
+            "cursor": first_page.next_cursor,
+            "sort": "name",
+        });
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let second_page: ResultPage = serde_json::from_value(result).unwrap();
+        assert_eq!(second_page.domains.len(), 1);
+        assert_eq!(second_page.domains[0].name.as_deref(), Some("👨‍🌾"));
+        assert!(second_page.next_cursor.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_tokenized() {
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let params =
+            serde_json::to_value(["J6QDztZCegYTWnGUYtjqVS9d7AZoS43UbEQmMcdGeP5s"]).unwrap();
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let result: ResultPage = serde_json::from_value(result).unwrap();
+        assert!(result.domains.iter().any(|item| item.tokenized));
+    }
+
     #[tokio::test]
     async fn integrated_test_0() {
         use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
@@ -101,8 +282,7 @@ mod tests {
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
             eprintln!("{:?}", result.result);
-            let value = result
-                .result
+            let value = result.result["domains"]
                 .as_array()
                 .unwrap()
                 .iter()
@@ -111,19 +291,23 @@ mod tests {
             assert_eq!(value.len(), 4);
             assert!(value.contains(&ResultItem {
                 key: "9B8y69VYEvLuwnaPdqNWL2wrV2XCLKrNAewC3FQEXptn".to_owned(),
-                name: "👨‍🌾".to_owned()
+                name: Some("👨‍🌾".to_owned()),
+                tokenized: false,
             }));
             assert!(value.contains(&ResultItem {
                 key: "BAW7NsKcY8SLr98ZNYcH2HeDvPBPE2EoyjuPKcJ9bW1d".to_owned(),
-                name: "9772".to_owned()
+                name: Some("9772".to_owned()),
+                tokenized: false,
             }));
             assert!(value.contains(&ResultItem {
                 key: "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb".to_owned(),
-                name: "bonfida".to_owned()
+                name: Some("bonfida".to_owned()),
+                tokenized: false,
             }));
             assert!(value.contains(&ResultItem {
                 key: "8xMJaFHqas1gzS7xLuWh298TDuBUw4hqLXL2ZFs376hH".to_owned(),
-                name: "springboks".to_owned()
+                name: Some("springboks".to_owned()),
+                tokenized: false,
             }));
         } else {
             let text = response.text().await.unwrap();
@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sns_sdk::{
+    derivation::get_domain_key,
+    record::{get_record_key, Record, RecordVersion},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::program_pack::Pack;
+use spl_name_service::state::NameRecordHeader;
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    get_domain_data::{format_v1_data, parse_record_v2, Encoding, QueryResult, ValidationIdFormat},
+    get_multiple_accounts_batched,
+    get_supported_records::SUPPORTED_RECORDS,
+    params::parse_params,
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    #[serde(default)]
+    encoding: Encoding,
+    #[serde(default)]
+    validation_id_format: ValidationIdFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordEntry {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub v1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub v2: Option<QueryResult>,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "encoding", "validationIdFormat"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+
+    let mut record_keys = Vec::with_capacity(SUPPORTED_RECORDS.len());
+    for record in SUPPORTED_RECORDS {
+        let v1_key = get_record_key(&params.domain, record, RecordVersion::V1)
+            .map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+        let v2_key = get_record_key(&params.domain, record, RecordVersion::V2)
+            .map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+        record_keys.push((record, v1_key, v2_key));
+    }
+    let mut keys = Vec::with_capacity(1 + 2 * record_keys.len());
+    keys.push(domain_key);
+    for (_, v1_key, v2_key) in &record_keys {
+        keys.push(*v1_key);
+        keys.push(*v2_key);
+    }
+
+    let mut accounts = get_multiple_accounts_batched(&rpc_client, &keys)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter();
+    let domain_account = accounts
+        .next()
+        .flatten()
+        .ok_or(trace!(ErrorType::InvalidDomain))?;
+    let domain_header =
+        NameRecordHeader::unpack_unchecked(&domain_account.data[..NameRecordHeader::LEN])
+            .map_err(|e| trace!(ErrorType::Generic, e))?;
+
+    let mut result = BTreeMap::new();
+    for (record, v1_key, _) in record_keys {
+        let v1 = accounts.next().flatten().and_then(|a| {
+            format_v1_data(&a.data, Some((record, v1_key)), params.encoding)
+                .map_err(|e| log_invalid_record(record, e))
+                .ok()
+        });
+        let v2 = accounts.next().flatten().and_then(|a| {
+            parse_record_v2(
+                &domain_header.owner,
+                &a.data,
+                record,
+                params.validation_id_format,
+            )
+            .map_err(|e| log_invalid_record(record, e))
+            .ok()
+        });
+        if v1.is_none() && v2.is_none() {
+            continue;
+        }
+        result.insert(record.as_str(), RecordEntry { v1, v2 });
+    }
+    let value = serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))?;
+    with_normalized_domain(&params.domain, value)
+}
+
+fn log_invalid_record(record: Record, error: crate::Error) {
+    log::warn!("Skipping invalid {} record: {error:?}", record.as_str());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn integrated_test_0() {
+        use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
+        use base64::Engine;
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let client = reqwest::Client::new();
+        let message = RpcMessage {
+            jsonrpc: JSON_RPC.to_owned(),
+            method: Method::GetAllRecords,
+            params: serde_json::to_value(["bonfida.sol"]).unwrap(),
+            id: serde_json::to_value(42u8).unwrap(),
+        };
+        eprintln!("{}", serde_json::to_string_pretty(&message).unwrap());
+        let post_request = client.post(&endpoint).json(&message).build().unwrap();
+        let response = client.execute(post_request).await.unwrap();
+        eprintln!("{:#?}", response);
+        if response.status().is_success() {
+            let result: RpcResponseOk<String> = response.json().await.unwrap();
+            let records: BTreeMap<String, RecordEntry> =
+                serde_json::from_value(result.result["value"].clone()).unwrap();
+            let github = records.get("github").unwrap().v1.as_ref().unwrap();
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(github)
+                .unwrap();
+            let s = std::str::from_utf8(&bytes[..27]).unwrap();
+            assert_eq!(s, "https://github.com/Bonfida/");
+        } else {
+            let text = response.text().await.unwrap();
+            eprintln!("Error body:\n {text}");
+            panic!()
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::Deserialize;
+use serde_json::Value;
+use sns_sdk::derivation::{get_domain_key, get_hashed_name, get_prefix, get_reverse_key, Domain};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::{instruction::NameRegistryInstruction, state::NameRecordHeader};
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    name_service::create_reverse,
+    params::parse_params,
+    transaction::{build_transaction, get_name_owner, get_transaction_result, TransactionOptions},
+};
+
+const DEFAULT_SPACE: u32 = 2_000;
+const MAX_SPACE: u32 = 10_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    parent: DomainName,
+    subdomain: String,
+    parent_owner: String,
+    owner: Option<String>,
+    space: Option<u32>,
+    #[serde(default)]
+    options: TransactionOptions,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &[
+        "parent",
+        "subdomain",
+        "parentOwner",
+        "owner",
+        "space",
+        "options",
+    ];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let parent_owner = Pubkey::from_str(&params.parent_owner)
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let owner = params
+        .owner
+        .map(|o| Pubkey::from_str(&o))
+        .transpose()
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?
+        .unwrap_or(parent_owner);
+    let space = params.space.unwrap_or(DEFAULT_SPACE);
+    if space > MAX_SPACE {
+        return Err(trace!(ErrorType::InvalidParameters, space));
+    }
+
+    let label =
+        DomainName::label(&params.subdomain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let parent = params.parent;
+    if parent.contains('.') {
+        return Err(trace!(ErrorType::InvalidDomain, parent));
+    }
+    let parent_key = get_domain_key(&parent).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let subdomain = format!("{label}.{parent}");
+    let subdomain_key =
+        get_domain_key(&subdomain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+
+    let current_parent_owner = get_name_owner(&rpc_client, &parent_key)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    if current_parent_owner != parent_owner {
+        return Err(trace!(
+            ErrorType::InvalidParameters,
+            "The parent owner does not own the parent domain"
+        ));
+    }
+    let subdomain_account = rpc_client
+        .get_account_with_commitment(&subdomain_key, rpc_client.commitment())
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .value;
+    if subdomain_account.is_some() {
+        return Err(trace!(
+            ErrorType::InvalidDomain,
+            "The subdomain already exists"
+        ));
+    }
+
+    let name = get_prefix(Domain::Sub) + &label;
+    let lamports = rpc_client
+        .get_minimum_balance_for_rent_exemption(NameRecordHeader::LEN + space as usize)
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+    let create_instruction = spl_name_service::instruction::create(
+        spl_name_service::id(),
+        NameRegistryInstruction::Create {
+            hashed_name: get_hashed_name(&name),
+            lamports,
+            space,
+        },
+        subdomain_key,
+        parent_owner,
+        owner,
+        None,
+        Some(parent_key),
+        Some(parent_owner),
+    )
+    .map_err(|e| trace!(ErrorType::Generic, e))?;
+    let reverse_key =
+        get_reverse_key(&subdomain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let reverse_instruction = create_reverse(
+        &name,
+        &reverse_key,
+        &parent_owner,
+        Some((&parent_key, &parent_owner)),
+    );
+    let transaction = build_transaction(
+        &rpc_client,
+        &[create_instruction, reverse_instruction],
+        &parent_owner,
+        &params.options,
+    )
+    .await
+    .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &params.options).await?;
+    with_normalized_domain(&subdomain, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn test_0() {
+        use solana_sdk::transaction::Transaction;
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let parent_owner = "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA";
+        let params = serde_json::json!({
+            "parent": "bonfida.sol",
+            "subdomain": "this-subdomain-does-not-exist",
+            "parentOwner": parent_owner,
+        });
+        let result = process(RpcClient::new(endpoint.clone()), params)
+            .await
+            .unwrap();
+        let bytes = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            result["value"].as_str().unwrap(),
+        )
+        .unwrap();
+        let transaction: Transaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(transaction.message.instructions.len(), 2);
+        assert_eq!(
+            transaction.message.account_keys[0].to_string(),
+            parent_owner
+        );
+
+        let params = serde_json::json!(["bonfida.sol", "dex.xyz", parent_owner]);
+        assert!(process(RpcClient::new(endpoint), params).await.is_err());
+    }
+}
@@ -14,13 +14,60 @@ use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
 use spl_name_service::state::NameRecordHeader;
 
-use super::{get_opt_string_from_value_array, get_string_from_value_array};
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+};
 
 #[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    domain: String,
+    domain: DomainName,
     record: Option<String>,
+    #[serde(default)]
+    encoding: Encoding,
+    #[serde(default)]
+    validation_id_format: ValidationIdFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Encoding {
+    #[default]
+    Base64,
+    Decoded,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationIdFormat {
+    #[default]
+    String,
+    Object,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum ValidationId {
+    String(String),
+    Object(Option<ValidationIdObject>),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ValidationIdObject {
+    Solana {
+        key: String,
+    },
+    Ethereum {
+        address: String,
+    },
+    #[serde(rename = "xchain", rename_all = "camelCase")]
+    XChain {
+        chain_id: u16,
+        chain_name: Option<String>,
+        owner_key: String,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -31,22 +78,22 @@ pub enum QueryResult {
     V2 {
         current_owner: String,
         content: String,
-        staleness_id: String,
+        staleness_id: ValidationId,
         staleness_validation: String,
-        roa_id: String,
+        roa_id: ValidationId,
         roa_validation: String,
+        #[serde(rename = "isStale")]
+        is_stale: bool,
+        #[serde(rename = "isRoaValid")]
+        is_roa_valid: bool,
     },
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "record", "encoding", "validationIdFormat"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            let record = get_opt_string_from_value_array(v, 1).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain, record })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -60,32 +107,59 @@ pub async fn get_domain_data(
     params: Params,
     record_version: RecordVersion,
 ) -> Result<Value, crate::Error> {
-    let Params { domain, record } = params;
+    let Params {
+        domain,
+        record,
+        encoding,
+        validation_id_format,
+    } = params;
+    let value = query_domain_data(
+        &rpc_client,
+        &domain,
+        record,
+        record_version,
+        encoding,
+        validation_id_format,
+    )
+    .await?;
+    with_normalized_domain(&domain, value)
+}
+
+async fn query_domain_data(
+    rpc_client: &RpcClient,
+    domain: &DomainName,
+    record: Option<String>,
+    record_version: RecordVersion,
+    encoding: Encoding,
+    validation_id_format: ValidationIdFormat,
+) -> Result<Value, crate::Error> {
     let record = record
-        .map(|s| Record::try_from_str(&s))
-        .transpose()
-        .map_err(|e| trace!(ErrorType::InvalidRecord, e))?
-        .map(|r| sns_sdk::record::get_record_key(&domain, r, record_version).map(|k| (r, k)))
-        .transpose()
-        .map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+        .map(|s| Record::try_from_str(&s).map_err(|e| trace!(ErrorType::InvalidRecord, e)))
+        .transpose()?
+        .map(|r| {
+            sns_sdk::record::get_record_key(domain, r, record_version)
+                .map(|k| (r, k))
+                .map_err(|e| trace!(ErrorType::InvalidDomain, e))
+        })
+        .transpose()?;
     let result = match (record, record_version) {
         (None, _) | (_, RecordVersion::V1) => {
             let account_key = Ok(record.map(|d| d.1)).transpose().unwrap_or_else(|| {
-                get_domain_key(&domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))
+                get_domain_key(domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))
             })?;
             let account = rpc_client
                 .get_account_with_commitment(&account_key, rpc_client.commitment())
                 .await
                 .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
                 .value;
-            let data = account.map(|a| {
-                base64::engine::general_purpose::STANDARD.encode(&a.data[NameRecordHeader::LEN..])
-            });
+            let data = account
+                .map(|a| format_v1_data(&a.data, record, encoding))
+                .transpose()?;
             QueryResult::V1(data)
         }
         (Some((record, record_key)), RecordVersion::V2) => {
             let domain_key =
-                get_domain_key(&domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+                get_domain_key(domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
             let accounts = rpc_client
                 .get_multiple_accounts(&[domain_key, record_key])
                 .await
@@ -107,50 +181,165 @@ pub async fn get_domain_data(
                 NameRecordHeader::unpack_unchecked(&domain_account.data[..NameRecordHeader::LEN])
                     .map_err(|e| trace!(ErrorType::Generic, e))?;
 
-            if record_account.data.len() < NameRecordHeader::LEN + RecordHeader::LEN {
-                return Err(trace!(ErrorType::InvalidRecord));
-            }
-            let record_v2_header =
-                sns_records::state::record_header::RecordHeader::from_buffer(&record_account.data);
-            let roa_validation =
-                Validation::try_from(record_v2_header.right_of_association_validation)
-                    .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
-            let roa_len = get_validation_length(roa_validation) as usize;
-            let staleness_validation = Validation::try_from(record_v2_header.staleness_validation)
-                .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
-            let staleness_len = get_validation_length(staleness_validation) as usize;
-            let staleness_id_offset = NameRecordHeader::LEN + RecordHeader::LEN;
-            let roa_offset = staleness_id_offset + staleness_len;
-            let content_offset = roa_offset + roa_len;
-            if record_account.data.len()
-                < content_offset + (record_v2_header.content_length as usize)
-            {
-                return Err(trace!(ErrorType::InvalidRecord));
-            }
-            let staleness_id = parse_validation_id(
-                &record_account.data[staleness_id_offset..],
-                staleness_validation,
-            )?;
-            let roa_id = parse_validation_id(&record_account.data[roa_offset..], roa_validation)?;
-            let data = sns_sdk::record::record_v2::deserialize_record_v2_content(
-                &record_account.data[content_offset..],
+            parse_record_v2(
+                &domain_header.owner,
+                &record_account.data,
                 record,
-            )
-            .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
-            QueryResult::V2 {
-                current_owner: domain_header.owner.to_string(),
-                content: data,
-                staleness_id,
-                staleness_validation: parse_validation(&staleness_validation).to_owned(),
-                roa_id,
-                roa_validation: parse_validation(&roa_validation).to_owned(),
-            }
+                validation_id_format,
+            )?
         }
     };
     serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))
 }
 
-fn parse_validation_id(buffer: &[u8], validation: Validation) -> Result<String, crate::Error> {
+pub fn format_v1_data(
+    data: &[u8],
+    record: Option<(Record, Pubkey)>,
+    encoding: Encoding,
+) -> Result<String, crate::Error> {
+    let data = &data[NameRecordHeader::LEN..];
+    match (encoding, record) {
+        (Encoding::Base64, _) => Ok(base64::engine::general_purpose::STANDARD.encode(data)),
+        (Encoding::Decoded, Some((record, record_key))) => {
+            sns_sdk::record::deserialize_record(data, record, &record_key)
+                .map_err(|e| trace!(ErrorType::InvalidRecord, e))
+        }
+        (Encoding::Decoded, None) => {
+            let end = data.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+            Ok(String::from_utf8_lossy(&data[..end]).into_owned())
+        }
+    }
+}
+
+pub fn parse_record_v2(
+    domain_owner: &Pubkey,
+    data: &[u8],
+    record: Record,
+    validation_id_format: ValidationIdFormat,
+) -> Result<QueryResult, crate::Error> {
+    if data.len() < NameRecordHeader::LEN + RecordHeader::LEN {
+        return Err(trace!(ErrorType::InvalidRecord));
+    }
+    let record_v2_header = RecordHeader::from_buffer(data);
+    let roa_validation = Validation::try_from(record_v2_header.right_of_association_validation)
+        .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    let roa_len = get_validation_length(roa_validation) as usize;
+    let staleness_validation = Validation::try_from(record_v2_header.staleness_validation)
+        .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    let staleness_len = get_validation_length(staleness_validation) as usize;
+    let staleness_id_offset = NameRecordHeader::LEN + RecordHeader::LEN;
+    let roa_offset = staleness_id_offset + staleness_len;
+    let content_offset = roa_offset + roa_len;
+    let content_end = content_offset + (record_v2_header.content_length as usize);
+    if data.len() < content_end {
+        return Err(trace!(ErrorType::InvalidRecord));
+    }
+    let is_stale = !matches!(staleness_validation, Validation::Solana)
+        || data[staleness_id_offset..roa_offset] != domain_owner.to_bytes();
+    let is_roa_valid = verify_right_of_association(
+        record,
+        roa_validation,
+        &data[roa_offset..content_offset],
+        &data[content_offset..content_end],
+    );
+    let staleness_id = parse_validation_id(
+        &data[staleness_id_offset..],
+        staleness_validation,
+        validation_id_format,
+    )?;
+    let roa_id = parse_validation_id(&data[roa_offset..], roa_validation, validation_id_format)?;
+    let content =
+        sns_sdk::record::record_v2::deserialize_record_v2_content(&data[content_offset..], record)
+            .map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    Ok(QueryResult::V2 {
+        current_owner: domain_owner.to_string(),
+        content,
+        staleness_id,
+        staleness_validation: parse_validation(&staleness_validation).to_owned(),
+        roa_id,
+        roa_validation: parse_validation(&roa_validation).to_owned(),
+        is_stale,
+        is_roa_valid,
+    })
+}
+
+fn verify_right_of_association(
+    record: Record,
+    validation: Validation,
+    roa_id: &[u8],
+    content: &[u8],
+) -> bool {
+    match (record, validation) {
+        (Record::Sol, Validation::Solana) => roa_id == content,
+        (Record::Eth | Record::Bsc | Record::Injective, Validation::Ethereum) => roa_id == content,
+        (_, Validation::XChain) => roa_id.get(2..) == Some(content),
+        _ => false,
+    }
+}
+
+fn parse_validation_id(
+    buffer: &[u8],
+    validation: Validation,
+    format: ValidationIdFormat,
+) -> Result<ValidationId, crate::Error> {
+    let result = match format {
+        ValidationIdFormat::String => {
+            ValidationId::String(format_validation_id(buffer, validation)?)
+        }
+        ValidationIdFormat::Object => {
+            ValidationId::Object(parse_validation_id_object(buffer, validation)?)
+        }
+    };
+    Ok(result)
+}
+
+fn parse_validation_id_object(
+    buffer: &[u8],
+    validation: Validation,
+) -> Result<Option<ValidationIdObject>, crate::Error> {
+    let result = match validation {
+        Validation::None | Validation::UnverifiedSolana => None,
+        Validation::Solana => Some(ValidationIdObject::Solana {
+            key: Pubkey::try_from(&buffer[..32])
+                .map_err(|e| trace!(ErrorType::InvalidRecord, e))?
+                .to_string(),
+        }),
+        Validation::Ethereum => Some(ValidationIdObject::Ethereum {
+            address: format!("0x{}", base16::encode_lower(&buffer[..20])),
+        }),
+        Validation::XChain => {
+            let chain_id = u16::from_le_bytes(buffer[..2].try_into().unwrap());
+            Some(ValidationIdObject::XChain {
+                chain_id,
+                chain_name: get_chain_name(chain_id).map(|n| n.to_owned()),
+                owner_key: format!("0x{}", base16::encode_lower(&buffer[2..34])),
+            })
+        }
+    };
+    Ok(result)
+}
+
+// Chain ids follow the Wormhole chain id registry
+fn get_chain_name(chain_id: u16) -> Option<&'static str> {
+    let name = match chain_id {
+        1 => "solana",
+        2 => "ethereum",
+        4 => "bsc",
+        5 => "polygon",
+        6 => "avalanche",
+        10 => "fantom",
+        19 => "injective",
+        21 => "sui",
+        22 => "aptos",
+        23 => "arbitrum",
+        24 => "optimism",
+        30 => "base",
+        _ => return None,
+    };
+    Some(name)
+}
+
+fn format_validation_id(buffer: &[u8], validation: Validation) -> Result<String, crate::Error> {
     let result = match validation {
         Validation::None | Validation::UnverifiedSolana => "".to_owned(),
         Validation::Solana => Pubkey::try_from(&buffer[..32])
@@ -197,7 +386,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             let bytes = base64::engine::general_purpose::STANDARD
                 .decode(value)
                 .unwrap();
@@ -239,15 +428,17 @@ mod tests {
             let res = get_domain_data(
                 rpc_client,
                 Params {
-                    domain: item.domain,
+                    domain: DomainName::new(&item.domain).unwrap(),
                     record: Some(item.record.as_str().to_owned()),
+                    encoding: Encoding::Base64,
+                    validation_id_format: ValidationIdFormat::String,
                 },
                 RecordVersion::V1,
             )
             .await
             .unwrap();
             let des = base64::engine::general_purpose::STANDARD
-                .decode(res.as_str().unwrap())
+                .decode(res["value"].as_str().unwrap())
                 .unwrap();
             let str = String::from_utf8(des).unwrap();
             let trimmed_str = str.trim_end_matches('\0').to_string();
@@ -261,19 +452,115 @@ mod tests {
         let res = get_domain_data(
             rpc_client,
             Params {
-                domain: String::from("wallet-guide-4"),
+                domain: DomainName::new("wallet-guide-4").unwrap(),
                 record: Some(Record::Sol.as_str().to_owned()),
+                encoding: Encoding::Base64,
+                validation_id_format: ValidationIdFormat::String,
             },
             RecordVersion::V1,
         )
         .await
         .unwrap();
         let des = base64::engine::general_purpose::STANDARD
-            .decode(res.as_str().unwrap())
+            .decode(res["value"].as_str().unwrap())
             .unwrap();
         assert_eq!(des[..32], *expected_pubkey.as_ref());
     }
 
+    #[tokio::test]
+    async fn test_record_v1_decoded() {
+        dotenv::dotenv().ok();
+
+        let expected_values = [
+            (
+                "🍍",
+                Record::Ipfs,
+                "QmbWqxBEKC3P8tqsKc98xmWNzrzDtRLMiMPL8wBuTGsMnR",
+            ),
+            ("🍍", Record::Arwv, "some-arweave-hash"),
+            (
+                "wallet-guide-4",
+                Record::Sol,
+                "Hf4daCT4tC2Vy9RCe9q8avT68yAsNJ1dQe6xiQqyGuqZ",
+            ),
+        ];
+
+        for (domain, record, value) in expected_values.into_iter() {
+            let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+            let rpc_client = RpcClient::new(endpoint);
+            let res = get_domain_data(
+                rpc_client,
+                Params {
+                    domain: DomainName::new(domain).unwrap(),
+                    record: Some(record.as_str().to_owned()),
+                    encoding: Encoding::Decoded,
+                    validation_id_format: ValidationIdFormat::String,
+                },
+                RecordVersion::V1,
+            )
+            .await
+            .unwrap();
+            assert_eq!(res["value"].as_str().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_validation_id_object() {
+        let mut buffer = vec![2, 0];
+        buffer.extend_from_slice(&[0xab; 32]);
+        let id =
+            parse_validation_id(&buffer, Validation::XChain, ValidationIdFormat::Object).unwrap();
+        assert_eq!(
+            serde_json::to_value(id).unwrap(),
+            serde_json::json!({
+                "type": "xchain",
+                "chainId": 2,
+                "chainName": "ethereum",
+                "ownerKey": format!("0x{}", "ab".repeat(32)),
+            })
+        );
+
+        let key = Pubkey::from_str("Hf4daCT4tC2Vy9RCe9q8avT68yAsNJ1dQe6xiQqyGuqZ").unwrap();
+        let id = parse_validation_id(key.as_ref(), Validation::Solana, ValidationIdFormat::Object)
+            .unwrap();
+        assert_eq!(
+            id,
+            ValidationId::Object(Some(ValidationIdObject::Solana {
+                key: key.to_string()
+            }))
+        );
+    }
+
+    #[test]
+    fn test_query_result_v2_keys() {
+        let result = QueryResult::V2 {
+            current_owner: String::new(),
+            content: String::new(),
+            staleness_id: ValidationId::String(String::new()),
+            staleness_validation: String::new(),
+            roa_id: ValidationId::String(String::new()),
+            roa_validation: String::new(),
+            is_stale: false,
+            is_roa_valid: false,
+        };
+        let value = serde_json::to_value(result).unwrap();
+        let mut keys = value.as_object().unwrap().keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(
+            keys,
+            [
+                "content",
+                "current_owner",
+                "isRoaValid",
+                "isStale",
+                "roa_id",
+                "roa_validation",
+                "staleness_id",
+                "staleness_validation"
+            ]
+        );
+    }
+
     #[tokio::test]
     async fn test_record_v2() {
         dotenv::dotenv().ok();
@@ -286,6 +573,8 @@ mod tests {
             pub staleness_validation: String,
             pub roa_id: String,
             pub roa_validation: String,
+            pub is_stale: bool,
+            pub is_roa_valid: bool,
         }
         let expected_values: Vec<Item> = vec![
             Item {
@@ -296,6 +585,8 @@ mod tests {
                 staleness_validation: String::from("Solana"),
                 roa_id: String::from(""),
                 roa_validation: String::from("None"),
+                is_stale: false,
+                is_roa_valid: false,
             },
             Item {
                 record: Record::Email,
@@ -305,6 +596,8 @@ mod tests {
                 staleness_validation: String::from("None"),
                 roa_id: String::from(""),
                 roa_validation: String::from("None"),
+                is_stale: true,
+                is_roa_valid: false,
             },
             Item {
                 record: Record::Url,
@@ -314,6 +607,8 @@ mod tests {
                 staleness_validation: String::from("None"),
                 roa_id: String::from(""),
                 roa_validation: String::from("None"),
+                is_stale: true,
+                is_roa_valid: false,
             },
             Item {
                 record: Record::Sol,
@@ -323,6 +618,8 @@ mod tests {
                 staleness_validation: String::from("Solana"),
                 roa_id: String::from("Hf4daCT4tC2Vy9RCe9q8avT68yAsNJ1dQe6xiQqyGuqZ"),
                 roa_validation: String::from("Solana"),
+                is_stale: false,
+                is_roa_valid: true,
             },
         ];
 
@@ -332,14 +629,16 @@ mod tests {
             let res = get_domain_data(
                 rpc_client,
                 Params {
-                    domain: item.domain,
+                    domain: DomainName::new(&item.domain).unwrap(),
                     record: Some(item.record.as_str().to_owned()),
+                    encoding: Encoding::Base64,
+                    validation_id_format: ValidationIdFormat::String,
                 },
                 RecordVersion::V2,
             )
             .await
             .unwrap();
-            let des = serde_json::from_value::<QueryResult>(res).unwrap();
+            let des = serde_json::from_value::<QueryResult>(res["value"].clone()).unwrap();
 
             match des {
                 QueryResult::V1(_) => panic!(),
@@ -350,16 +649,20 @@ mod tests {
                     staleness_validation,
                     roa_id,
                     roa_validation,
+                    is_stale,
+                    is_roa_valid,
                 } => {
                     assert_eq!(content, item.value);
                     assert_eq!(staleness_validation, item.staleness_validation);
-                    assert_eq!(staleness_id, item.staleness_id);
+                    assert_eq!(staleness_id, ValidationId::String(item.staleness_id));
                     assert_eq!(
                         current_owner,
                         "Fxuoy3gFjfJALhwkRcuKjRdechcgffUApeYAfMWck6w8"
                     );
-                    assert_eq!(roa_id, item.roa_id);
-                    assert_eq!(roa_validation, item.roa_validation)
+                    assert_eq!(roa_id, ValidationId::String(item.roa_id));
+                    assert_eq!(roa_validation, item.roa_validation);
+                    assert_eq!(is_stale, item.is_stale);
+                    assert_eq!(is_roa_valid, item.is_roa_valid);
                 }
             }
         }
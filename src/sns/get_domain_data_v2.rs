@@ -34,7 +34,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             let bytes = base64::engine::general_purpose::STANDARD
                 .decode(value)
                 .unwrap();
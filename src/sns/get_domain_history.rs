@@ -0,0 +1,572 @@
+use std::{collections::HashMap, str::FromStr};
+
+use crate::{append_trace, trace, ErrorType};
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sns_sdk::{
+    derivation::{get_domain_key, NAME_TOKENIZER_ID},
+    record::{Record, RecordVersion},
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig, rpc_response::RpcConfirmedTransactionStatusWithSignature,
+};
+use solana_sdk::{bs58, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedConfirmedTransactionWithStatusMeta, UiInstruction,
+    UiTransactionEncoding,
+};
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    get_record_key_from_domain_key,
+    get_supported_records::SUPPORTED_RECORDS,
+    params::parse_params,
+};
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1_000;
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+
+// spl-name-service instruction tags
+const NAME_SERVICE_CREATE: u8 = 0;
+const NAME_SERVICE_UPDATE: u8 = 1;
+const NAME_SERVICE_TRANSFER: u8 = 2;
+const NAME_SERVICE_DELETE: u8 = 3;
+const NAME_SERVICE_REALLOC: u8 = 4;
+
+// sns-records instruction tags
+const RECORDS_ALLOCATE: u8 = 0;
+const RECORDS_ALLOCATE_AND_POST: u8 = 1;
+const RECORDS_EDIT: u8 = 2;
+const RECORDS_VALIDATE_SOLANA_SIGNATURE: u8 = 3;
+const RECORDS_VALIDATE_ETHEREUM_SIGNATURE: u8 = 4;
+const RECORDS_DELETE: u8 = 5;
+const RECORDS_WRITE_ROA: u8 = 6;
+const RECORDS_UNVERIFY_ROA: u8 = 7;
+
+// name-tokenizer instruction tags
+const TOKENIZER_CREATE_NFT: u8 = 2;
+const TOKENIZER_REDEEM_NFT: u8 = 3;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    include_records: Option<bool>,
+    limit: Option<usize>,
+    before: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    Create,
+    Update,
+    Transfer,
+    Delete,
+    Realloc,
+    RecordCreate,
+    RecordUpdate,
+    RecordValidation,
+    RecordDelete,
+    Tokenize,
+    Redeem,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEvent {
+    signature: String,
+    slot: u64,
+    block_time: Option<i64>,
+    #[serde(rename = "type")]
+    kind: EventKind,
+    account: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    record: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultPage {
+    events: Vec<HistoryEvent>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Tracked {
+    Domain,
+    RecordV1(Record),
+    RecordV2(Record),
+}
+
+#[derive(Debug)]
+struct DecodedEvent {
+    kind: EventKind,
+    account: Pubkey,
+    record: Option<Record>,
+    owner: Option<Pubkey>,
+}
+
+struct DecodedInstruction {
+    program_id: Pubkey,
+    accounts: Vec<Pubkey>,
+    data: Vec<u8>,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "includeRecords", "limit", "before"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let limit = params.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+    if limit == 0 || limit > MAX_PAGE_SIZE {
+        return Err(trace!(ErrorType::InvalidParameters, limit));
+    }
+    let before = params
+        .before
+        .as_deref()
+        .map(Signature::from_str)
+        .transpose()
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+
+    let mut tracked = HashMap::from([(domain_key, Tracked::Domain)]);
+    let mut addresses = vec![domain_key];
+    // Every derived record key is queried, deleted records keep their history
+    if params.include_records.unwrap_or(true) {
+        for record in SUPPORTED_RECORDS {
+            for (version, kind) in [
+                (RecordVersion::V1, Tracked::RecordV1(record)),
+                (RecordVersion::V2, Tracked::RecordV2(record)),
+            ] {
+                let key = get_record_key_from_domain_key(&domain_key, record, version);
+                tracked.insert(key, kind);
+                addresses.push(key);
+            }
+        }
+    }
+
+    let commitment = history_commitment(&rpc_client);
+    let signatures = stream::iter(addresses)
+        .map(|address| {
+            let rpc_client = &rpc_client;
+            async move {
+                let config = GetConfirmedSignaturesForAddress2Config {
+                    before,
+                    until: None,
+                    limit: Some(limit),
+                    commitment: Some(commitment),
+                };
+                rpc_client
+                    .get_signatures_for_address_with_config(&address, config)
+                    .await
+                    .map_err(|e| trace!(ErrorType::SolanaRpcError, e))
+            }
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect::<Vec<_>>()
+        .await?;
+    let (signatures, next_cursor) = merge_signatures(signatures, limit);
+
+    let transactions = stream::iter(signatures.iter().filter(|s| s.err.is_none()))
+        .map(|status| {
+            let rpc_client = &rpc_client;
+            async move {
+                let signature = Signature::from_str(&status.signature)
+                    .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+                let config = RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Base64),
+                    commitment: Some(commitment),
+                    max_supported_transaction_version: Some(0),
+                };
+                let transaction = rpc_client
+                    .get_transaction_with_config(&signature, config)
+                    .await
+                    .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+                Ok::<_, crate::Error>((status, transaction))
+            }
+        })
+        .buffered(MAX_CONCURRENT_REQUESTS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let mut events = vec![];
+    for (status, transaction) in transactions {
+        let instructions = get_instructions(&transaction).map_err(|e| append_trace!(e))?;
+        for event in decode_events(&instructions, &tracked) {
+            events.push(HistoryEvent {
+                signature: status.signature.clone(),
+                slot: status.slot,
+                block_time: status.block_time,
+                kind: event.kind,
+                account: event.account.to_string(),
+                record: event.record.map(|r| r.as_str().to_owned()),
+                owner: event.owner.map(|o| o.to_string()),
+            });
+        }
+    }
+    let page = ResultPage {
+        events,
+        next_cursor,
+    };
+    let value = serde_json::to_value(page).map_err(|e| trace!(ErrorType::Generic, e))?;
+    with_normalized_domain(&params.domain, value)
+}
+
+// Transaction history is not available at the processed commitment level
+fn history_commitment(rpc_client: &RpcClient) -> CommitmentConfig {
+    let commitment = rpc_client.commitment();
+    if commitment.is_at_least_confirmed() {
+        commitment
+    } else {
+        CommitmentConfig::confirmed()
+    }
+}
+
+// Signatures are ordered like getSignaturesForAddress, by descending slot and then by descending
+// signature within a slot. The `before` cursor skips the signatures of its slot that sort above it,
+// so the same-slot signatures of other addresses are not lost between pages.
+fn merge_signatures(
+    pages: Vec<Vec<RpcConfirmedTransactionStatusWithSignature>>,
+    limit: usize,
+) -> (
+    Vec<RpcConfirmedTransactionStatusWithSignature>,
+    Option<String>,
+) {
+    let has_more = pages.iter().any(|p| p.len() >= limit);
+    let mut signatures: Vec<RpcConfirmedTransactionStatusWithSignature> = vec![];
+    for status in pages.into_iter().flatten() {
+        if !signatures.iter().any(|s| s.signature == status.signature) {
+            signatures.push(status);
+        }
+    }
+    signatures.sort_by_cached_key(|s| {
+        (
+            std::cmp::Reverse(s.slot),
+            std::cmp::Reverse(Signature::from_str(&s.signature).ok()),
+        )
+    });
+    let has_more = has_more || signatures.len() > limit;
+    signatures.truncate(limit);
+    let next_cursor = if has_more {
+        signatures.last().map(|s| s.signature.clone())
+    } else {
+        None
+    };
+    (signatures, next_cursor)
+}
+
+fn get_instructions(
+    transaction: &EncodedConfirmedTransactionWithStatusMeta,
+) -> Result<Vec<DecodedInstruction>, crate::Error> {
+    let decoded = transaction
+        .transaction
+        .transaction
+        .decode()
+        .ok_or(trace!(ErrorType::SolanaRpcError))?;
+    let meta = transaction.transaction.meta.as_ref();
+    let mut account_keys = decoded.message.static_account_keys().to_vec();
+    if let Some(OptionSerializer::Some(loaded)) = meta.map(|m| &m.loaded_addresses) {
+        for key in loaded.writable.iter().chain(loaded.readonly.iter()) {
+            account_keys
+                .push(Pubkey::from_str(key).map_err(|e| trace!(ErrorType::SolanaRpcError, e))?);
+        }
+    }
+    let inner_instructions = match meta.map(|m| &m.inner_instructions) {
+        Some(OptionSerializer::Some(inner)) => inner.as_slice(),
+        _ => &[],
+    };
+
+    let resolve = |program_id_index: u8, accounts: &[u8], data: Vec<u8>| {
+        let key = |i: u8| account_keys.get(i as usize).copied();
+        Some(DecodedInstruction {
+            program_id: key(program_id_index)?,
+            accounts: accounts.iter().map(|i| key(*i)).collect::<Option<_>>()?,
+            data,
+        })
+    };
+    let mut result = vec![];
+    for (index, instruction) in decoded.message.instructions().iter().enumerate() {
+        result.extend(resolve(
+            instruction.program_id_index,
+            &instruction.accounts,
+            instruction.data.clone(),
+        ));
+        let inner = inner_instructions
+            .iter()
+            .filter(|i| i.index as usize == index)
+            .flat_map(|i| i.instructions.iter());
+        for instruction in inner {
+            let UiInstruction::Compiled(instruction) = instruction else {
+                continue;
+            };
+            let Ok(data) = bs58::decode(&instruction.data).into_vec() else {
+                continue;
+            };
+            result.extend(resolve(
+                instruction.program_id_index,
+                &instruction.accounts,
+                data,
+            ));
+        }
+    }
+    Ok(result)
+}
+
+fn decode_events(
+    instructions: &[DecodedInstruction],
+    tracked: &HashMap<Pubkey, Tracked>,
+) -> Vec<DecodedEvent> {
+    let mut events = vec![];
+    for instruction in instructions {
+        let Some(tag) = instruction.data.first().copied() else {
+            continue;
+        };
+        let event = if instruction.program_id == spl_name_service::id() {
+            decode_name_service(instruction, tag, tracked)
+        } else if instruction.program_id == sns_records::ID {
+            decode_records(instruction, tag, tracked)
+        } else if instruction.program_id == NAME_TOKENIZER_ID {
+            decode_tokenizer(instruction, tag, tracked)
+        } else {
+            None
+        };
+        events.extend(event);
+    }
+    events
+}
+
+fn decode_name_service(
+    instruction: &DecodedInstruction,
+    tag: u8,
+    tracked: &HashMap<Pubkey, Tracked>,
+) -> Option<DecodedEvent> {
+    let name_account_index = match tag {
+        NAME_SERVICE_CREATE | NAME_SERVICE_REALLOC => 2,
+        _ => 0,
+    };
+    let account = *instruction.accounts.get(name_account_index)?;
+    // V2 records are written through the records program, which is decoded separately
+    let kind = match (tracked.get(&account)?, tag) {
+        (Tracked::Domain, NAME_SERVICE_CREATE) => EventKind::Create,
+        (Tracked::Domain, NAME_SERVICE_UPDATE) => EventKind::Update,
+        (Tracked::Domain, NAME_SERVICE_TRANSFER) => EventKind::Transfer,
+        (Tracked::Domain, NAME_SERVICE_DELETE) => EventKind::Delete,
+        (Tracked::Domain, NAME_SERVICE_REALLOC) => EventKind::Realloc,
+        (Tracked::RecordV1(_), NAME_SERVICE_CREATE) => EventKind::RecordCreate,
+        (Tracked::RecordV1(_), NAME_SERVICE_UPDATE | NAME_SERVICE_REALLOC) => {
+            EventKind::RecordUpdate
+        }
+        (Tracked::RecordV1(_), NAME_SERVICE_DELETE) => EventKind::RecordDelete,
+        _ => return None,
+    };
+    let owner = match kind {
+        EventKind::Create => instruction.accounts.get(3).copied(),
+        EventKind::Transfer => instruction
+            .data
+            .get(1..33)
+            .and_then(|b| Pubkey::try_from(b).ok()),
+        _ => None,
+    };
+    Some(DecodedEvent {
+        kind,
+        account,
+        record: tracked_record(tracked, &account),
+        owner,
+    })
+}
+
+fn decode_records(
+    instruction: &DecodedInstruction,
+    tag: u8,
+    tracked: &HashMap<Pubkey, Tracked>,
+) -> Option<DecodedEvent> {
+    let account = *instruction
+        .accounts
+        .iter()
+        .find(|k| matches!(tracked.get(*k), Some(Tracked::RecordV2(_))))?;
+    let kind = match tag {
+        RECORDS_ALLOCATE | RECORDS_ALLOCATE_AND_POST => EventKind::RecordCreate,
+        RECORDS_EDIT => EventKind::RecordUpdate,
+        RECORDS_VALIDATE_SOLANA_SIGNATURE
+        | RECORDS_VALIDATE_ETHEREUM_SIGNATURE
+        | RECORDS_WRITE_ROA
+        | RECORDS_UNVERIFY_ROA => EventKind::RecordValidation,
+        RECORDS_DELETE => EventKind::RecordDelete,
+        _ => return None,
+    };
+    Some(DecodedEvent {
+        kind,
+        account,
+        record: tracked_record(tracked, &account),
+        owner: None,
+    })
+}
+
+fn decode_tokenizer(
+    instruction: &DecodedInstruction,
+    tag: u8,
+    tracked: &HashMap<Pubkey, Tracked>,
+) -> Option<DecodedEvent> {
+    let account = *instruction
+        .accounts
+        .iter()
+        .find(|k| matches!(tracked.get(*k), Some(Tracked::Domain)))?;
+    let kind = match tag {
+        TOKENIZER_CREATE_NFT => EventKind::Tokenize,
+        TOKENIZER_REDEEM_NFT => EventKind::Redeem,
+        _ => return None,
+    };
+    Some(DecodedEvent {
+        kind,
+        account,
+        record: None,
+        owner: None,
+    })
+}
+
+fn tracked_record(tracked: &HashMap<Pubkey, Tracked>, key: &Pubkey) -> Option<Record> {
+    match tracked.get(key)? {
+        Tracked::Domain => None,
+        Tracked::RecordV1(record) | Tracked::RecordV2(record) => Some(*record),
+    }
+}
+
+#[cfg(test)]
+impl PartialEq for DecodedEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.kind == other.kind
+            && self.account == other.account
+            && self.record.map(|r| r.as_str()) == other.record.map(|r| r.as_str())
+            && self.owner == other.owner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn to_decoded(instruction: solana_sdk::instruction::Instruction) -> DecodedInstruction {
+        DecodedInstruction {
+            program_id: instruction.program_id,
+            accounts: instruction.accounts.iter().map(|a| a.pubkey).collect(),
+            data: instruction.data,
+        }
+    }
+
+    #[test]
+    fn test_decode_transfer() {
+        let domain_key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let new_owner = Pubkey::new_unique();
+        let tracked = HashMap::from([(domain_key, Tracked::Domain)]);
+        let instruction = spl_name_service::instruction::transfer(
+            spl_name_service::id(),
+            new_owner,
+            domain_key,
+            owner,
+            None,
+        )
+        .unwrap();
+        let events = decode_events(&[to_decoded(instruction)], &tracked);
+        assert_eq!(
+            events,
+            vec![DecodedEvent {
+                kind: EventKind::Transfer,
+                account: domain_key,
+                record: None,
+                owner: Some(new_owner),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_decode_record_update() {
+        let record_key = Pubkey::new_unique();
+        let tracked = HashMap::from([(record_key, Tracked::RecordV1(Record::Github))]);
+        let update = |account| DecodedInstruction {
+            program_id: spl_name_service::id(),
+            accounts: vec![account, Pubkey::new_unique()],
+            data: vec![NAME_SERVICE_UPDATE, 0, 0, 0, 0],
+        };
+        let events = decode_events(
+            &[update(record_key), update(Pubkey::new_unique())],
+            &tracked,
+        );
+        assert_eq!(
+            events,
+            vec![DecodedEvent {
+                kind: EventKind::RecordUpdate,
+                account: record_key,
+                record: Some(Record::Github),
+                owner: None,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_merge_signatures() {
+        let status = |signature: &str, slot| RpcConfirmedTransactionStatusWithSignature {
+            signature: signature.to_owned(),
+            slot,
+            err: None,
+            memo: None,
+            block_time: None,
+            confirmation_status: None,
+        };
+        let pages = vec![
+            vec![status("a", 10), status("c", 5)],
+            vec![status("b", 8), status("c", 5), status("d", 1)],
+        ];
+        let (signatures, next_cursor) = merge_signatures(pages, 3);
+        let signatures = signatures
+            .iter()
+            .map(|s| s.signature.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(signatures, ["a", "b", "c"]);
+        assert_eq!(next_cursor.as_deref(), Some("c"));
+
+        // Same-slot signatures are ordered by descending signature
+        let low = Signature::from([1; 64]).to_string();
+        let high = Signature::from([2; 64]).to_string();
+        let pages = vec![vec![status(&low, 5)], vec![status(&high, 5)]];
+        let (signatures, next_cursor) = merge_signatures(pages, 1);
+        assert_eq!(signatures[0].signature, high);
+        assert_eq!(next_cursor, Some(high));
+    }
+
+    #[tokio::test]
+    async fn integrated_test_0() {
+        use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let client = reqwest::Client::new();
+        let message = RpcMessage {
+            jsonrpc: JSON_RPC.to_owned(),
+            method: Method::GetDomainHistory,
+            params: serde_json::json!({ "domain": "bonfida.sol", "limit": 10 }),
+            id: serde_json::to_value(42u8).unwrap(),
+        };
+        let post_request = client.post(&endpoint).json(&message).build().unwrap();
+        let response = client.execute(post_request).await.unwrap();
+        if response.status().is_success() {
+            let result: RpcResponseOk<String> = response.json().await.unwrap();
+            let page: ResultPage = serde_json::from_value(result.result["value"].clone()).unwrap();
+            assert!(page.events.iter().all(|e| e.slot > 0));
+        } else {
+            let text = response.text().await.unwrap();
+            eprintln!("Error body:\n {text}");
+            panic!()
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::str::FromStr;
+
+use crate::{append_trace, config::CONFIG, trace, ErrorType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sns_sdk::derivation::get_domain_key;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    get_multiple_accounts_batched,
+    params::parse_params,
+    pricing::{
+        get_price_feed, get_supported_mint, get_token_amount, get_usd_price, parse_pyth_price,
+        validate_label, SupportedMint, USDC_MINT,
+    },
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    mint: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DomainPrice {
+    domain: String,
+    available: bool,
+    length: usize,
+    usd_price: u64,
+    usdc: TokenPrice,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mint: Option<TokenPrice>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenPrice {
+    mint: String,
+    amount: u64,
+    decimals: u8,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "mint"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let label = params.domain.to_string();
+    let length = validate_label(&label).map_err(|e| append_trace!(e))?;
+    let domain_key = get_domain_key(&label).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+
+    let usdc = get_supported_mint(&USDC_MINT).ok_or(trace!(ErrorType::Generic))?;
+    let mint = params
+        .mint
+        .map(|m| {
+            let m = Pubkey::from_str(&m).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+            get_supported_mint(&m).ok_or(trace!(ErrorType::InvalidParameters, "Unsupported mint"))
+        })
+        .transpose()?;
+
+    let mut feeds = vec![get_price_feed(&usdc.mint).ok_or(trace!(ErrorType::Generic))?];
+    if let Some(mint) = mint {
+        let feed = get_price_feed(&mint.mint)
+            .ok_or(trace!(ErrorType::InvalidParameters, "Unsupported mint"))?;
+        feeds.push(feed);
+    }
+    let available = get_multiple_accounts_batched(&rpc_client, &[domain_key])
+        .await
+        .map_err(|e| append_trace!(e))?
+        .pop()
+        .flatten()
+        .is_none();
+    // The slot of the response dates the freshness of the price accounts, cached reads included
+    let response = rpc_client
+        .get_multiple_accounts_with_commitment(&feeds, rpc_client.commitment())
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+    let slot = response.context.slot;
+    let mut price_accounts = response.value.into_iter();
+    let usd_price = get_usd_price(&CONFIG.usd_prices, length);
+    let usdc = get_token_price(usdc, price_accounts.next().flatten(), usd_price, slot)?;
+    let mint = mint
+        .map(|m| get_token_price(m, price_accounts.next().flatten(), usd_price, slot))
+        .transpose()?;
+
+    let result = DomainPrice {
+        domain: label,
+        available,
+        length,
+        usd_price,
+        usdc,
+        mint,
+    };
+    let value = serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))?;
+    with_normalized_domain(&params.domain, value)
+}
+
+fn get_token_price(
+    supported_mint: &SupportedMint,
+    price_account: Option<solana_sdk::account::Account>,
+    usd_price: u64,
+    slot: u64,
+) -> Result<TokenPrice, crate::Error> {
+    let price_account = price_account.ok_or(trace!(
+        ErrorType::SolanaRpcError,
+        "Price feed account not found"
+    ))?;
+    let now = chrono::Utc::now().timestamp();
+    let token_price = parse_pyth_price(&price_account.data, slot, now, CONFIG.max_price_age)
+        .map_err(|e| append_trace!(e))?;
+    Ok(TokenPrice {
+        mint: supported_mint.mint.to_string(),
+        amount: get_token_amount(usd_price, token_price, supported_mint.decimals),
+        decimals: supported_mint.decimals,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn integrated_test_0() {
+        use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let client = reqwest::Client::new();
+        let message = RpcMessage {
+            jsonrpc: JSON_RPC.to_owned(),
+            method: Method::GetDomainPrice,
+            params: serde_json::to_value(["bonfida.sol"]).unwrap(),
+            id: serde_json::to_value(42u8).unwrap(),
+        };
+        eprintln!("{}", serde_json::to_string_pretty(&message).unwrap());
+        let post_request = client.post(&endpoint).json(&message).build().unwrap();
+        let response = client.execute(post_request).await.unwrap();
+        eprintln!("{:#?}", response);
+        if response.status().is_success() {
+            let result: RpcResponseOk<String> = response.json().await.unwrap();
+            let value: DomainPrice =
+                serde_json::from_value(result.result["value"].clone()).unwrap();
+            assert!(!value.available);
+            assert_eq!(value.length, 7);
+            assert_eq!(value.usd_price, 20);
+            assert!(value.usdc.amount > 0);
+            assert!(value.mint.is_none());
+        } else {
+            let text = response.text().await.unwrap();
+            eprintln!("Error body:\n {text}");
+            panic!()
+        }
+    }
+}
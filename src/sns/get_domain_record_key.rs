@@ -36,7 +36,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             assert_eq!(value, "4sQDE98ZzQ23Rygb7tx1HhXQiuxswKhSBvECCREW35Ei");
         } else {
             let text = response.text().await.unwrap();
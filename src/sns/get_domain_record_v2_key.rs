@@ -4,23 +4,23 @@ use serde_json::Value;
 use sns_sdk::record::{get_record_key, Record, RecordVersion};
 use solana_client::nonblocking::rpc_client::RpcClient;
 
-use super::get_string_from_value_array;
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+};
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    pub domain: String,
+    pub domain: DomainName,
     pub record: String,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "record"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            let record = get_string_from_value_array(v, 1).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain, record })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -37,8 +37,7 @@ pub fn get_domain_record_key(
         Record::try_from_str(&params.record).map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
     let domain_record_key = get_record_key(&params.domain, record, record_version)
         .map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
-    Ok(serde_json::to_value(domain_record_key.to_string())
-        .map_err(|e| trace!(ErrorType::Generic, e)))?
+    with_normalized_domain(&params.domain, Value::from(domain_record_key.to_string()))
 }
 
 #[cfg(test)]
@@ -61,7 +60,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             assert_eq!(value, "4sQDE98ZzQ23Rygb7tx1HhXQiuxswKhSBvECCREW35Ei");
         } else {
             let text = response.text().await.unwrap();
@@ -80,7 +79,7 @@ mod tests {
         let items: Vec<Item> = vec![
             Item {
                 params: Params {
-                    domain: String::from("domain1"),
+                    domain: DomainName::new("domain1").unwrap(),
                     record: String::from("SOL"),
                 },
                 version: RecordVersion::V1,
@@ -88,7 +87,7 @@ mod tests {
             },
             Item {
                 params: Params {
-                    domain: String::from("domain1"),
+                    domain: DomainName::new("domain1").unwrap(),
                     record: String::from("SOL"),
                 },
                 version: RecordVersion::V2,
@@ -97,8 +96,8 @@ mod tests {
         ];
         for item in items.into_iter() {
             let res = get_domain_record_key(item.params, item.version).unwrap();
-            let res = res.as_str().unwrap();
-            assert_eq!(res, item.expected_result)
+            assert_eq!(res["domain"], "domain1");
+            assert_eq!(res["value"].as_str().unwrap(), item.expected_result)
         }
     }
 }
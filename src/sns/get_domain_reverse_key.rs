@@ -4,21 +4,22 @@ use serde_json::Value;
 use sns_sdk::derivation::get_reverse_key;
 use solana_client::nonblocking::rpc_client::RpcClient;
 
-use super::get_string_from_value_array;
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+};
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    domain: String,
+    domain: DomainName,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domain"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -26,8 +27,7 @@ pub async fn process(_rpc_client: RpcClient, params: Value) -> Result<Value, cra
     let params = Params::deserialize(params)?;
     let reverse_domain_key =
         get_reverse_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
-    Ok(serde_json::to_value(reverse_domain_key.to_string())
-        .map_err(|e| trace!(ErrorType::Generic, e)))?
+    with_normalized_domain(&params.domain, Value::from(reverse_domain_key.to_string()))
 }
 
 #[cfg(test)]
@@ -49,7 +49,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             assert_eq!(value, "DqgmWxe2PPrfy45Ja3UPyFGwcbRzkRuwXt3NyxjX8krg");
         } else {
             let text = response.text().await.unwrap();
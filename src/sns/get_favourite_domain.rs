@@ -7,27 +7,30 @@ use sns_sdk::non_blocking::resolve;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-use super::get_string_from_value_array;
+use super::{params::parse_params, tokenization};
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
     owner: String,
+    #[serde(default)]
+    ignore_stale: bool,
 }
 
-#[derive(Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Debug)]
+#[serde(rename_all = "camelCase")]
 pub struct ResultItem {
     name: String,
     key: String,
+    current_owner: Option<String>,
+    stale: bool,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["owner", "ignoreStale"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let owner = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { owner })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -39,23 +42,54 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
     let favourite_domain_key = resolve::get_favourite_domain(&rpc_client, &owner)
         .await
         .map_err(|e| trace!(ErrorType::Generic, e))?;
-    let result = if let Some(domain_key) = favourite_domain_key {
-        let name = resolve::resolve_reverse(&rpc_client, &domain_key)
-            .await
-            .map_err(|e| trace!(ErrorType::Generic, e))?
-            .ok_or(trace!(ErrorType::ReverseRecordNotFound))?;
-        Some(ResultItem {
-            name,
-            key: domain_key.to_string(),
-        })
-    } else {
-        None
+    let Some(domain_key) = favourite_domain_key else {
+        return Ok(Value::Null);
+    };
+    // Staleness is known before the reverse lookup, which is skipped for ignored stale domains
+    let current_owner = tokenization::get_domain_owners(&rpc_client, &[domain_key])
+        .await
+        .map_err(|e| append_trace!(e))?
+        .pop()
+        .flatten()
+        .map(|o| o.owner);
+    let stale = current_owner != Some(owner);
+    if stale && params.ignore_stale {
+        return Ok(Value::Null);
+    }
+    let name = resolve::resolve_reverse(&rpc_client, &domain_key)
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?
+        .ok_or(trace!(ErrorType::ReverseRecordNotFound))?;
+    let result = ResultItem {
+        name,
+        key: domain_key.to_string(),
+        current_owner: current_owner.map(|o| o.to_string()),
+        stale,
     };
     Ok(serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e)))?
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stale() {
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let owner = "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA";
+        let params = serde_json::json!({ "owner": owner, "ignoreStale": true });
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let result: ResultItem = serde_json::from_value(result).unwrap();
+        assert_eq!(
+            result,
+            ResultItem {
+                name: "bonfida".to_owned(),
+                key: "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb".to_owned(),
+                current_owner: Some(owner.to_owned()),
+                stale: false,
+            }
+        );
+    }
 
     #[tokio::test]
     async fn integrated_test_0() {
@@ -0,0 +1,145 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sns_sdk::{
+    favourite_domain::{derive_favourite_domain_key, FavouriteDomain},
+    non_blocking::resolve,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{get_multiple_accounts_batched, params::parse_params, tokenization};
+
+const MAX_WALLETS: usize = 100;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    wallets: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct ResultItem {
+    wallet: String,
+    domain: Option<PrimaryDomain>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug)]
+pub struct PrimaryDomain {
+    name: Option<String>,
+    key: String,
+    stale: bool,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["wallets"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    if params.wallets.len() > MAX_WALLETS {
+        return Err(trace!(ErrorType::InvalidParameters, params.wallets.len()));
+    }
+    let wallets = params
+        .wallets
+        .iter()
+        .map(|w| Pubkey::from_str(w).map_err(|e| trace!(ErrorType::InvalidParameters, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let favourite_keys = wallets
+        .iter()
+        .map(derive_favourite_domain_key)
+        .collect::<Vec<_>>();
+    let favourites = get_multiple_accounts_batched(&rpc_client, &favourite_keys)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter()
+        .map(|a| {
+            a.and_then(|a| FavouriteDomain::parse(&a.data).ok())
+                .map(|f| f.name_account)
+        })
+        .collect::<Vec<_>>();
+
+    let domain_keys = favourites.iter().flatten().copied().collect::<Vec<_>>();
+    let owners = tokenization::get_domain_owners(&rpc_client, &domain_keys)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let names = resolve::resolve_reverse_batch(&rpc_client, &domain_keys)
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+
+    let mut domains = domain_keys.into_iter().zip(owners).zip(names);
+    let mut result = Vec::with_capacity(wallets.len());
+    for (wallet, favourite) in wallets.iter().zip(favourites) {
+        let domain = if favourite.is_some() {
+            let ((key, owner), name) = domains.next().ok_or(trace!(ErrorType::Generic))?;
+            Some(PrimaryDomain {
+                name,
+                key: key.to_string(),
+                stale: owner.map(|o| o.owner) != Some(*wallet),
+            })
+        } else {
+            None
+        };
+        result.push(ResultItem {
+            wallet: wallet.to_string(),
+            domain,
+        });
+    }
+    serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn integrated_test_0() {
+        use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let client = reqwest::Client::new();
+        let message = RpcMessage {
+            jsonrpc: JSON_RPC.to_owned(),
+            method: Method::GetPrimaryDomains,
+            params: serde_json::json!([[
+                "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA",
+                "11111111111111111111111111111111"
+            ]]),
+            id: serde_json::to_value(42u8).unwrap(),
+        };
+        eprintln!("{}", serde_json::to_string_pretty(&message).unwrap());
+        let post_request = client.post(&endpoint).json(&message).build().unwrap();
+        let response = client.execute(post_request).await.unwrap();
+        eprintln!("{:#?}", response);
+        if response.status().is_success() {
+            let result: RpcResponseOk<String> = response.json().await.unwrap();
+            let value: Vec<ResultItem> = serde_json::from_value(result.result).unwrap();
+            assert_eq!(
+                value,
+                vec![
+                    ResultItem {
+                        wallet: "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA".to_owned(),
+                        domain: Some(PrimaryDomain {
+                            name: Some("bonfida".to_owned()),
+                            key: "Crf8hzfthWGbGbLTVCiqRqV5MVnbpHB1L9KQMd6gsinb".to_owned(),
+                            stale: false,
+                        }),
+                    },
+                    ResultItem {
+                        wallet: "11111111111111111111111111111111".to_owned(),
+                        domain: None,
+                    },
+                ]
+            );
+        } else {
+            let text = response.text().await.unwrap();
+            eprintln!("Error body:\n {text}");
+            panic!()
+        }
+    }
+}
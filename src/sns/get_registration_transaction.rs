@@ -1,7 +1,6 @@
 use std::str::FromStr;
 
 use crate::{append_trace, trace, ErrorType};
-use base64::Engine;
 use serde::Deserialize;
 use serde_json::Value;
 use sns_sdk::non_blocking::register::register_domain_name;
@@ -9,41 +8,39 @@ use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
 use super::{
-    get_int_from_value_array, get_opt_string_from_value_array, get_string_from_value_array,
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+    transaction::{
+        build_transaction, decompile_instructions, get_transaction_result, TransactionOptions,
+    },
 };
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    domain: String,
+    domain: DomainName,
     buyer: String,
     buyer_token_account: String,
     space: u32,
     mint: Option<String>,
     referrer_key: Option<String>,
+    #[serde(default)]
+    options: TransactionOptions,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &[
+        "domain",
+        "buyer",
+        "buyerTokenAccount",
+        "space",
+        "mint",
+        "referrerKey",
+        "options",
+    ];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            let buyer = get_string_from_value_array(v, 1).map_err(|e| append_trace!(e))?;
-            let buyer_token_account =
-                get_string_from_value_array(v, 2).map_err(|e| append_trace!(e))?;
-            let space = get_int_from_value_array(v, 3).map_err(|e| append_trace!(e))?;
-            let mint = get_opt_string_from_value_array(v, 4).map_err(|e| append_trace!(e))?;
-            let referrer_key =
-                get_opt_string_from_value_array(v, 5).map_err(|e| append_trace!(e))?;
-            Ok(Self {
-                domain,
-                buyer,
-                buyer_token_account,
-                space,
-                mint,
-                referrer_key,
-            })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -56,6 +53,7 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
         space,
         mint,
         referrer_key,
+        options,
     } = params;
     let buyer = Pubkey::from_str(&buyer).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
     let buyer_token_account = Pubkey::from_str(&buyer_token_account)
@@ -80,9 +78,10 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
     )
     .await
     .map_err(|e| trace!((&e).into(), e))?;
-    let serialized_transaction =
-        bincode::serialize(&register_transaction).map_err(|e| trace!(ErrorType::Generic, e))?;
-    let encoded_transaction =
-        base64::engine::general_purpose::STANDARD.encode(serialized_transaction);
-    serde_json::to_value(encoded_transaction).map_err(|e| trace!(ErrorType::Generic, e))
+    let instructions = decompile_instructions(&register_transaction.message);
+    let transaction = build_transaction(&rpc_client, &instructions, &buyer, &options)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &options).await?;
+    with_normalized_domain(&domain, value)
 }
@@ -0,0 +1,55 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::Deserialize;
+use serde_json::Value;
+use sns_sdk::derivation::{get_domain_key, ROOT_DOMAIN_ACCOUNT};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    favourite::register_favourite,
+    params::parse_params,
+    transaction::{build_transaction, get_name_header, get_transaction_result, TransactionOptions},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    owner: String,
+    payer: Option<String>,
+    #[serde(default)]
+    options: TransactionOptions,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "owner", "payer", "options"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let options = params
+        .options
+        .with_payer(params.payer)
+        .map_err(|e| append_trace!(e))?;
+    let owner =
+        Pubkey::from_str(&params.owner).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let header = get_name_header(&rpc_client, &domain_key)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let parent = (header.parent_name != ROOT_DOMAIN_ACCOUNT).then_some(header.parent_name);
+    let instruction = register_favourite(&domain_key, &owner, parent.as_ref());
+    let transaction = build_transaction(&rpc_client, &[instruction], &owner, &options)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &options).await?;
+    with_normalized_domain(&params.domain, value)
+}
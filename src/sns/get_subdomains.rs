@@ -1,25 +1,74 @@
 use crate::{append_trace, trace, ErrorType};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use sns_sdk::{derivation, non_blocking::resolve};
+use sns_sdk::{
+    derivation::{self, derive, get_prefix, Domain, REVERSE_LOOKUP_CLASS},
+    non_blocking::resolve,
+    record::{Record, RecordVersion},
+};
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
 
-use super::get_string_from_value_array;
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    find_domains_by_record::Version,
+    get_domain_data::{format_v1_data, parse_record_v2, Encoding, QueryResult, ValidationIdFormat},
+    get_multiple_accounts_batched, get_record_key_from_domain_key,
+    params::parse_params,
+};
+
+const MAX_DEPTH: u8 = 3;
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    domain: String,
+    domain: DomainName,
+    extended: Option<bool>,
+    include_sol_record: Option<bool>,
+    depth: Option<u8>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultItem {
+    name: String,
+    label: String,
+    parent: String,
+    depth: u8,
+    key: String,
+    owner: Option<String>,
+    has_reverse: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sol_record: Option<Option<SolRecord>>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct SolRecord {
+    content: String,
+    version: Version,
+}
+
+struct Subdomain {
+    name: String,
+    label: String,
+    parent: String,
+    parent_key: Pubkey,
+    depth: u8,
+    key: Pubkey,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "extended", "includeSolRecord", "depth"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain })
-        } else {
-            serde_json::from_value(value)
-                .map_err(|e| trace!(crate::ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+
+    /// `includeSolRecord` and `depth` imply the extended format unless `extended` is `false`
+    fn is_extended(&self) -> bool {
+        self.extended
+            .unwrap_or(self.include_sol_record.is_some() || self.depth.is_some())
     }
 }
 
@@ -27,14 +76,232 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
     let params = Params::deserialize(params)?;
     let key = derivation::get_domain_key(&params.domain)
         .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
-    let subdomains = resolve::get_subdomains(&rpc_client, &key)
+    if !params.is_extended() {
+        let subdomains = resolve::get_subdomains(&rpc_client, &key)
+            .await
+            .map_err(|e| trace!(ErrorType::Generic, e))?;
+        let value = serde_json::to_value(subdomains).map_err(|e| trace!(ErrorType::Generic, e))?;
+        return with_normalized_domain(&params.domain, value);
+    }
+
+    let max_depth = params.depth.unwrap_or(1);
+    if max_depth == 0 || max_depth > MAX_DEPTH {
+        return Err(trace!(ErrorType::InvalidParameters, max_depth));
+    }
+    let include_sol_record = params.include_sol_record.unwrap_or_default();
+    let domain = params.domain.to_string();
+
+    let mut subdomains = vec![];
+    let mut parents = vec![(domain, key)];
+    for depth in 1..=max_depth {
+        let mut next_parents = vec![];
+        for (parent, parent_key) in parents {
+            let labels = resolve::get_subdomains(&rpc_client, &parent_key)
+                .await
+                .map_err(|e| trace!(ErrorType::Generic, e))?;
+            for label in labels {
+                let key = derive(&(get_prefix(Domain::Sub) + &label), &parent_key, None);
+                let name = format!("{label}.{parent}");
+                next_parents.push((name.clone(), key));
+                subdomains.push(Subdomain {
+                    name,
+                    label,
+                    parent: parent.clone(),
+                    parent_key,
+                    depth,
+                    key,
+                });
+            }
+        }
+        parents = next_parents;
+    }
+
+    let keys_per_subdomain = if include_sol_record { 4 } else { 2 };
+    let mut keys = Vec::with_capacity(keys_per_subdomain * subdomains.len());
+    for subdomain in &subdomains {
+        keys.push(subdomain.key);
+        keys.push(derive(
+            &subdomain.key.to_string(),
+            &subdomain.parent_key,
+            Some(REVERSE_LOOKUP_CLASS),
+        ));
+        if include_sol_record {
+            keys.push(get_record_key_from_domain_key(
+                &subdomain.key,
+                Record::Sol,
+                RecordVersion::V1,
+            ));
+            keys.push(get_record_key_from_domain_key(
+                &subdomain.key,
+                Record::Sol,
+                RecordVersion::V2,
+            ));
+        }
+    }
+    let accounts = get_multiple_accounts_batched(&rpc_client, &keys)
         .await
-        .map_err(|e| trace!(ErrorType::Generic, e))?;
-    Ok(serde_json::to_value(subdomains).map_err(|e| trace!(ErrorType::Generic, e)))?
+        .map_err(|e| append_trace!(e))?;
+
+    let mut result = Vec::with_capacity(subdomains.len());
+    for (subdomain, (accounts, keys)) in subdomains.into_iter().zip(
+        accounts
+            .chunks(keys_per_subdomain)
+            .zip(keys.chunks(keys_per_subdomain)),
+    ) {
+        let owner = accounts[0]
+            .as_ref()
+            .map(|a| NameRecordHeader::unpack_unchecked(&a.data[..NameRecordHeader::LEN]))
+            .transpose()
+            .map_err(|e| trace!(ErrorType::Generic, e))?
+            .map(|h| h.owner);
+        let sol_record = if include_sol_record {
+            Some(get_sol_record(owner.as_ref(), &accounts[2..], keys[2]))
+        } else {
+            None
+        };
+        result.push(ResultItem {
+            name: subdomain.name,
+            label: subdomain.label,
+            parent: subdomain.parent,
+            depth: subdomain.depth,
+            key: subdomain.key.to_string(),
+            owner: owner.map(|o| o.to_string()),
+            has_reverse: accounts[1].is_some(),
+            sol_record,
+        });
+    }
+    let value = serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))?;
+    with_normalized_domain(&params.domain, value)
+}
+
+// A V2 record is only used when it is up to date and its right of association is verified,
+// otherwise the V1 record is returned
+fn get_sol_record(
+    owner: Option<&Pubkey>,
+    accounts: &[Option<Account>],
+    v1_key: Pubkey,
+) -> Option<SolRecord> {
+    let v2 = owner
+        .zip(accounts[1].as_ref())
+        .and_then(|(owner, account)| {
+            parse_record_v2(
+                owner,
+                &account.data,
+                Record::Sol,
+                ValidationIdFormat::String,
+            )
+            .ok()
+        });
+    if let Some(QueryResult::V2 {
+        content,
+        is_stale: false,
+        is_roa_valid: true,
+        ..
+    }) = v2
+    {
+        return Some(SolRecord {
+            content,
+            version: Version::V2,
+        });
+    }
+    let content = accounts[0].as_ref().and_then(|account| {
+        format_v1_data(
+            &account.data,
+            Some((Record::Sol, v1_key)),
+            Encoding::Decoded,
+        )
+        .ok()
+    })?;
+    Some(SolRecord {
+        content,
+        version: Version::V1,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_extended() {
+        let params = |value| Params::deserialize(value).unwrap().is_extended();
+        assert!(!params(serde_json::json!({ "domain": "bonfida" })));
+        assert!(params(
+            serde_json::json!({ "domain": "bonfida", "depth": 2 })
+        ));
+        assert!(!params(serde_json::json!({
+            "domain": "bonfida",
+            "extended": false,
+            "depth": 2,
+            "includeSolRecord": true,
+        })));
+    }
+
+    #[test]
+    fn test_key_derivation() {
+        let parent_key = derivation::get_domain_key("bonfida").unwrap();
+        let key = derive(&(get_prefix(Domain::Sub) + "dex"), &parent_key, None);
+        assert_eq!(key, derivation::get_domain_key("dex.bonfida").unwrap());
+        let reverse_key = derive(&key.to_string(), &parent_key, Some(REVERSE_LOOKUP_CLASS));
+        assert_eq!(
+            reverse_key,
+            derivation::get_reverse_key("dex.bonfida").unwrap()
+        );
+    }
+
+    fn sol_record_v2(staleness_id: &Pubkey, roa_id: &Pubkey, address: &Pubkey) -> Account {
+        let solana = sns_records::state::validation::Validation::Solana as u16;
+        let mut data = vec![0; NameRecordHeader::LEN];
+        data.extend_from_slice(&solana.to_le_bytes());
+        data.extend_from_slice(&solana.to_le_bytes());
+        data.extend_from_slice(&32u32.to_le_bytes());
+        data.extend_from_slice(staleness_id.as_ref());
+        data.extend_from_slice(roa_id.as_ref());
+        data.extend_from_slice(address.as_ref());
+        Account {
+            data,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_get_sol_record() {
+        let owner = Pubkey::new_unique();
+        let address = Pubkey::new_unique();
+        let v1_key = Pubkey::new_unique();
+
+        let accounts = [None, Some(sol_record_v2(&owner, &address, &address))];
+        let sol_record = get_sol_record(Some(&owner), &accounts, v1_key).unwrap();
+        assert_eq!(sol_record.content, address.to_string());
+        assert_eq!(sol_record.version, Version::V2);
+
+        // Stale, then with an unverified right of association
+        let accounts = [None, Some(sol_record_v2(&address, &address, &address))];
+        assert!(get_sol_record(Some(&owner), &accounts, v1_key).is_none());
+        let accounts = [None, Some(sol_record_v2(&owner, &owner, &address))];
+        assert!(get_sol_record(Some(&owner), &accounts, v1_key).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_extended() {
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let params = serde_json::json!({ "domain": "bonfida.sol", "extended": true });
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let result: Vec<ResultItem> = serde_json::from_value(result["value"].clone()).unwrap();
+        assert_eq!(result.len(), 3);
+        let dex = result.iter().find(|s| s.label == "dex").unwrap();
+        assert_eq!(dex.name, "dex.bonfida");
+        assert_eq!(dex.parent, "bonfida");
+        assert_eq!(dex.depth, 1);
+        assert_eq!(
+            dex.key,
+            derivation::get_domain_key("dex.bonfida.sol")
+                .unwrap()
+                .to_string()
+        );
+        assert!(dex.owner.is_some());
+    }
+
     #[tokio::test]
     async fn integrated_test_0() {
         use crate::sns::{Method, RpcMessage, RpcResponseOk, JSON_RPC};
@@ -52,8 +319,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result
-                .result
+            let value = result.result["value"]
                 .as_array()
                 .unwrap()
                 .iter()
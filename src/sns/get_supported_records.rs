@@ -1,45 +1,55 @@
-use crate::{trace, ErrorType};
+use crate::{append_trace, trace, ErrorType};
 use serde::Deserialize;
 use serde_json::Value;
 use sns_sdk::record::Record;
 use solana_client::nonblocking::rpc_client::RpcClient;
 
+use super::params::parse_params;
+
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {}
 
 impl Params {
-    pub fn deserialize(_value: Value) -> Result<Self, crate::Error> {
-        Ok(Self {})
+    const FIELDS: &'static [&'static str] = &[];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
+pub const SUPPORTED_RECORDS: [Record; 24] = [
+    Record::Ipfs,
+    Record::Arwv,
+    Record::Sol,
+    Record::Eth,
+    Record::Btc,
+    Record::Ltc,
+    Record::Doge,
+    Record::Email,
+    Record::Url,
+    Record::Discord,
+    Record::Github,
+    Record::Reddit,
+    Record::Twitter,
+    Record::Telegram,
+    Record::Pic,
+    Record::Shdw,
+    Record::Point,
+    Record::Bsc,
+    Record::Injective,
+    Record::Backpack,
+    Record::A,
+    Record::AAAA,
+    Record::CNAME,
+    Record::TXT,
+];
+
 pub async fn process(_rpc_client: RpcClient, _params: Value) -> Result<Value, crate::Error> {
-    let supported_records = [
-        Record::Ipfs.as_str(),
-        Record::Arwv.as_str(),
-        Record::Sol.as_str(),
-        Record::Eth.as_str(),
-        Record::Btc.as_str(),
-        Record::Ltc.as_str(),
-        Record::Doge.as_str(),
-        Record::Email.as_str(),
-        Record::Url.as_str(),
-        Record::Discord.as_str(),
-        Record::Github.as_str(),
-        Record::Reddit.as_str(),
-        Record::Twitter.as_str(),
-        Record::Telegram.as_str(),
-        Record::Pic.as_str(),
-        Record::Shdw.as_str(),
-        Record::Point.as_str(),
-        Record::Bsc.as_str(),
-        Record::Injective.as_str(),
-        Record::Backpack.as_str(),
-        Record::A.as_str(),
-        Record::AAAA.as_str(),
-        Record::CNAME.as_str(),
-        Record::TXT.as_str(),
-    ];
+    let supported_records = SUPPORTED_RECORDS
+        .iter()
+        .map(|r| r.as_str())
+        .collect::<Vec<_>>();
     Ok(serde_json::to_value(supported_records).map_err(|e| trace!(ErrorType::Generic, e)))?
 }
 
@@ -0,0 +1,121 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::Deserialize;
+use serde_json::Value;
+use sns_sdk::derivation::get_domain_key;
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{instruction::AccountMeta, pubkey::Pubkey};
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+    transaction::{
+        build_transaction, get_name_header, get_name_owner, get_transaction_result,
+        TransactionOptions,
+    },
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    new_owner: String,
+    #[serde(default)]
+    as_parent_owner: bool,
+    payer: Option<String>,
+    #[serde(default)]
+    options: TransactionOptions,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] =
+        &["domain", "newOwner", "asParentOwner", "payer", "options"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let options = params
+        .options
+        .with_payer(params.payer)
+        .map_err(|e| append_trace!(e))?;
+    let new_owner =
+        Pubkey::from_str(&params.new_owner).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let header = get_name_header(&rpc_client, &domain_key)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let class = (header.class != Pubkey::default()).then_some(header.class);
+
+    let instruction = if params.as_parent_owner {
+        if header.parent_name == Pubkey::default() {
+            return Err(trace!(
+                ErrorType::InvalidParameters,
+                "Domain does not have a parent"
+            ));
+        }
+        let parent_owner = get_name_owner(&rpc_client, &header.parent_name)
+            .await
+            .map_err(|e| append_trace!(e))?;
+        // The parent name account is expected after the class slot, which is padded when unset
+        let mut instruction = spl_name_service::instruction::transfer(
+            spl_name_service::id(),
+            new_owner,
+            domain_key,
+            parent_owner,
+            Some(header.class),
+        )
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+        if class.is_none() {
+            instruction.accounts[2].is_signer = false;
+        }
+        instruction
+            .accounts
+            .push(AccountMeta::new_readonly(header.parent_name, false));
+        instruction
+    } else {
+        spl_name_service::instruction::transfer(
+            spl_name_service::id(),
+            new_owner,
+            domain_key,
+            header.owner,
+            class,
+        )
+        .map_err(|e| trace!(ErrorType::Generic, e))?
+    };
+    let signer = instruction.accounts[1].pubkey;
+    let transaction = build_transaction(&rpc_client, &[instruction], &signer, &options)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &options).await?;
+    with_normalized_domain(&params.domain, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn test_0() {
+        use solana_sdk::transaction::Transaction;
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let new_owner = "11111111111111111111111111111111";
+        let params = serde_json::json!({ "domain": "bonfida.sol", "newOwner": new_owner });
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let bytes = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            result["value"].as_str().unwrap(),
+        )
+        .unwrap();
+        let transaction: Transaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(
+            transaction.message.account_keys[0].to_string(),
+            "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA"
+        );
+        assert_eq!(transaction.message.header.num_required_signatures, 1);
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{append_trace, trace, ErrorType};
+use serde::Deserialize;
+use serde_json::Value;
+use sns_records::instruction_auto::edit_record;
+use sns_sdk::record::record_v2::serialize_record_v2_content;
+use solana_client::nonblocking::rpc_client::RpcClient;
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+    record_v2_transaction::{RecordV2Context, RecordV2Target},
+    transaction::{build_transaction, get_transaction_result, TransactionOptions},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    record: String,
+    content: String,
+    payer: Option<String>,
+    #[serde(default)]
+    options: TransactionOptions,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "record", "content", "payer", "options"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let options = params
+        .options
+        .with_payer(params.payer)
+        .map_err(|e| append_trace!(e))?;
+    let target = RecordV2Target::new(&params.domain, &params.record, options.fee_payer.as_deref())?;
+    let content = serialize_record_v2_content(&params.content, target.record)
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let ctx = RecordV2Context::new(&rpc_client, target)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let instruction = edit_record(
+        sns_records::ID,
+        edit_record::Accounts {
+            system_program: &ctx.system_program,
+            spl_name_service_program: &ctx.spl_name_service_program,
+            fee_payer: &ctx.fee_payer,
+            record: &ctx.record_key,
+            domain: &ctx.domain_key,
+            domain_owner: &ctx.domain_owner,
+            central_state: &ctx.central_state,
+        },
+        edit_record::Params {
+            record: ctx.record_name(),
+            content,
+        },
+    );
+    let transaction = build_transaction(&rpc_client, &[instruction], &ctx.fee_payer, &options)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &options).await?;
+    with_normalized_domain(&params.domain, value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn test_0() {
+        use solana_sdk::transaction::Transaction;
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let params = serde_json::json!(["bonfida.sol", "url", "https://sns.id"]);
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let bytes = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            result["value"].as_str().unwrap(),
+        )
+        .unwrap();
+        let transaction: Transaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(transaction.message.instructions.len(), 1);
+        assert_eq!(
+            transaction.message.account_keys[0].to_string(),
+            "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA"
+        );
+    }
+}
@@ -0,0 +1,197 @@
+use std::str::FromStr;
+
+use crate::{append_trace, trace, ErrorType};
+use serde::Deserialize;
+use serde_json::Value;
+use sns_records::{
+    instruction_auto::{validate_ethereum_signature, validate_solana_signature},
+    state::validation::Validation,
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+    record_v2_transaction::{RecordV2Context, RecordV2Target},
+    transaction::{build_transaction, get_transaction_result, TransactionOptions},
+};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    record: String,
+    validation: ValidationKind,
+    verifier: Option<String>,
+    signature: Option<String>,
+    expected_pubkey: Option<String>,
+    payer: Option<String>,
+    #[serde(default)]
+    options: TransactionOptions,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationKind {
+    Staleness,
+    Solana,
+    Ethereum,
+}
+
+enum SignatureValidation {
+    Solana {
+        staleness: bool,
+        verifier: Option<Pubkey>,
+    },
+    Ethereum {
+        signature: Vec<u8>,
+        expected_pubkey: Vec<u8>,
+    },
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &[
+        "domain",
+        "record",
+        "validation",
+        "verifier",
+        "signature",
+        "expectedPubkey",
+        "payer",
+        "options",
+    ];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crate::Error> {
+    let params = Params::deserialize(params)?;
+    let options = params
+        .options
+        .with_payer(params.payer)
+        .map_err(|e| append_trace!(e))?;
+    let target = RecordV2Target::new(&params.domain, &params.record, options.fee_payer.as_deref())?;
+    let verifier = params
+        .verifier
+        .as_deref()
+        .map(Pubkey::from_str)
+        .transpose()
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let validation = match params.validation {
+        ValidationKind::Staleness => SignatureValidation::Solana {
+            staleness: true,
+            verifier,
+        },
+        ValidationKind::Solana => SignatureValidation::Solana {
+            staleness: false,
+            verifier: Some(verifier.ok_or(trace!(ErrorType::MissingParameters, "verifier"))?),
+        },
+        ValidationKind::Ethereum => SignatureValidation::Ethereum {
+            signature: decode_hex(params.signature.as_deref(), "signature")?,
+            expected_pubkey: decode_hex(params.expected_pubkey.as_deref(), "expectedPubkey")?,
+        },
+    };
+    let ctx = RecordV2Context::new(&rpc_client, target)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let instruction = match validation {
+        SignatureValidation::Solana {
+            staleness,
+            verifier,
+        } => validate_solana_signature(
+            sns_records::ID,
+            validate_solana_signature::Accounts {
+                system_program: &ctx.system_program,
+                spl_name_service_program: &ctx.spl_name_service_program,
+                fee_payer: &ctx.fee_payer,
+                record: &ctx.record_key,
+                domain: &ctx.domain_key,
+                domain_owner: &ctx.domain_owner,
+                central_state: &ctx.central_state,
+                // The staleness is validated by the domain owner by default
+                verifier: &verifier.unwrap_or(ctx.domain_owner),
+            },
+            validate_solana_signature::Params { staleness },
+        ),
+        SignatureValidation::Ethereum {
+            signature,
+            expected_pubkey,
+        } => validate_ethereum_signature(
+            sns_records::ID,
+            validate_ethereum_signature::Accounts {
+                system_program: &ctx.system_program,
+                spl_name_service_program: &ctx.spl_name_service_program,
+                fee_payer: &ctx.fee_payer,
+                record: &ctx.record_key,
+                domain: &ctx.domain_key,
+                domain_owner: &ctx.domain_owner,
+                central_state: &ctx.central_state,
+            },
+            validate_ethereum_signature::Params {
+                validation: Validation::Ethereum,
+                signature,
+                expected_pubkey,
+            },
+        ),
+    };
+    let transaction = build_transaction(&rpc_client, &[instruction], &ctx.fee_payer, &options)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let value = get_transaction_result(&rpc_client, &transaction, &options).await?;
+    with_normalized_domain(&params.domain, value)
+}
+
+fn decode_hex(value: Option<&str>, name: &str) -> Result<Vec<u8>, crate::Error> {
+    let value = value.ok_or(trace!(ErrorType::MissingParameters, name))?;
+    base16::decode(value.strip_prefix("0x").unwrap_or(value))
+        .map_err(|e| trace!(ErrorType::InvalidParameters, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[tokio::test]
+    async fn test_0() {
+        use solana_sdk::transaction::Transaction;
+        let endpoint = std::env::var("TEST_QUICKNODE_ENDPOINT").unwrap();
+        let params = serde_json::json!({ "domain": "bonfida.sol", "record": "url", "validation": "staleness" });
+        let result = process(RpcClient::new(endpoint), params).await.unwrap();
+        let bytes = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            result["value"].as_str().unwrap(),
+        )
+        .unwrap();
+        let transaction: Transaction = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(transaction.message.instructions.len(), 1);
+        assert_eq!(
+            transaction.message.account_keys[0].to_string(),
+            "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_missing_validation_parameters() {
+        // Parameters are validated before any RPC call
+        let endpoint = "http://localhost:8899".to_owned();
+        let params = serde_json::json!({
+            "domain": "bonfida.sol",
+            "record": "SOL",
+            "validation": "solana",
+        });
+        let error = process(RpcClient::new(endpoint.clone()), params)
+            .await
+            .unwrap_err();
+        assert!(matches!(error.ty, ErrorType::MissingParameters));
+        let params = serde_json::json!({
+            "domain": "bonfida.sol",
+            "record": "ETH",
+            "validation": "ethereum",
+            "signature": "0x00",
+        });
+        let error = process(RpcClient::new(endpoint), params).await.unwrap_err();
+        assert!(matches!(error.ty, ErrorType::MissingParameters));
+    }
+}
@@ -0,0 +1,43 @@
+use sns_sdk::{derivation::ROOT_DOMAIN_ACCOUNT, register::REGISTER_PROGRAM_ID};
+use solana_sdk::{
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    system_program, sysvar,
+};
+
+const CREATE_REVERSE_TAG: u8 = 12;
+
+/// Registrar instruction creating the reverse lookup account of a subdomain, which the SDK does
+/// not provide
+pub fn create_reverse(
+    name: &str,
+    reverse_key: &Pubkey,
+    fee_payer: &Pubkey,
+    parent: Option<(&Pubkey, &Pubkey)>,
+) -> Instruction {
+    let central_state =
+        Pubkey::find_program_address(&[REGISTER_PROGRAM_ID.as_ref()], &REGISTER_PROGRAM_ID).0;
+    let mut accounts = vec![
+        AccountMeta::new_readonly(spl_name_service::id(), false),
+        AccountMeta::new_readonly(ROOT_DOMAIN_ACCOUNT, false),
+        AccountMeta::new(*reverse_key, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+        AccountMeta::new_readonly(central_state, false),
+        AccountMeta::new(*fee_payer, true),
+        AccountMeta::new_readonly(sysvar::rent::ID, false),
+    ];
+    if let Some((parent_key, parent_owner)) = parent {
+        accounts.push(AccountMeta::new(*parent_key, false));
+        accounts.push(AccountMeta::new(*parent_owner, true));
+    }
+    // Borsh encoding of the name parameter
+    let mut data = Vec::with_capacity(5 + name.len());
+    data.push(CREATE_REVERSE_TAG);
+    data.extend_from_slice(&(name.len() as u32).to_le_bytes());
+    data.extend_from_slice(name.as_bytes());
+    Instruction {
+        program_id: REGISTER_PROGRAM_ID,
+        accounts,
+        data,
+    }
+}
@@ -0,0 +1,182 @@
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+
+use crate::{trace, ErrorType};
+
+/// Parses method parameters given either as a positional array, whose entries map to `fields`
+/// in order, or as an object with camelCase or snake_case keys. Null entries are treated as absent.
+/// Only the top-level keys are normalized, nested values such as record contents are passed as is.
+pub fn parse_params<T: DeserializeOwned>(value: Value, fields: &[&str]) -> Result<T, crate::Error> {
+    let object = match value {
+        Value::Array(array) => {
+            if array.len() > fields.len() {
+                return Err(trace!(ErrorType::InvalidParameters, array.len()).with_data(
+                    error_data(
+                        None,
+                        &format!("expected at most {} positional parameters", fields.len()),
+                    ),
+                ));
+            }
+            fields
+                .iter()
+                .zip(array)
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<Map<_, _>>()
+        }
+        Value::Object(object) => {
+            let mut result = Map::with_capacity(object.len());
+            for (key, value) in object {
+                if value.is_null() {
+                    continue;
+                }
+                let key = to_camel_case(&key);
+                if result.contains_key(&key) {
+                    return Err(trace!(ErrorType::InvalidParameters, key)
+                        .with_data(error_data(Some(&key), "duplicate parameter")));
+                }
+                result.insert(key, value);
+            }
+            result
+        }
+        Value::Null => Map::new(),
+        _ => {
+            return Err(trace!(ErrorType::InvalidParameters)
+                .with_data(error_data(None, "expected an array or an object")))
+        }
+    };
+    // Required parameters are the absent fields the root struct reports as missing
+    let absent = fields
+        .iter()
+        .filter(|f| !object.contains_key(**f))
+        .copied()
+        .collect::<Vec<_>>();
+    serde_path_to_error::deserialize(Value::Object(object)).map_err(|e| {
+        let path = e.path().to_string();
+        let reason = e.inner().to_string();
+        let missing = (path == ".")
+            .then(|| {
+                absent
+                    .iter()
+                    .find(|f| reason == format!("missing field `{f}`"))
+            })
+            .flatten();
+        match missing {
+            Some(field) => trace!(ErrorType::MissingParameters, reason)
+                .with_data(error_data(Some(field), "missing parameter")),
+            None => trace!(ErrorType::InvalidParameters, reason)
+                .with_data(error_data((path != ".").then_some(&path), &reason)),
+        }
+    })
+}
+
+fn error_data(parameter: Option<&str>, reason: &str) -> Value {
+    serde_json::json!({ "parameter": parameter, "reason": reason })
+}
+
+pub fn to_camel_case(key: &str) -> String {
+    let mut result = String::with_capacity(key.len());
+    let mut uppercase_next = false;
+    for c in key.chars() {
+        if c == '_' && !result.is_empty() {
+            uppercase_next = true;
+        } else if uppercase_next {
+            result.extend(c.to_uppercase());
+            uppercase_next = false;
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    struct Params {
+        domain: String,
+        buyer_token_account: Option<String>,
+        #[serde(default)]
+        space: u32,
+    }
+
+    const FIELDS: &[&str] = &["domain", "buyerTokenAccount", "space"];
+
+    #[test]
+    fn test_parse_params() {
+        let expected = Params {
+            domain: "bonfida".to_owned(),
+            buyer_token_account: Some("key".to_owned()),
+            space: 0,
+        };
+        let positional: Params =
+            parse_params(serde_json::json!(["bonfida", "key", null]), FIELDS).unwrap();
+        assert_eq!(positional, expected);
+        let camel: Params = parse_params(
+            serde_json::json!({ "domain": "bonfida", "buyerTokenAccount": "key" }),
+            FIELDS,
+        )
+        .unwrap();
+        assert_eq!(camel, expected);
+        let snake: Params = parse_params(
+            serde_json::json!({ "domain": "bonfida", "buyer_token_account": "key" }),
+            FIELDS,
+        )
+        .unwrap();
+        assert_eq!(snake, expected);
+    }
+
+    #[test]
+    fn test_parse_params_errors() {
+        let err = parse_params::<Params>(serde_json::json!({ "space": 1 }), FIELDS).unwrap_err();
+        assert!(matches!(err.ty, ErrorType::MissingParameters));
+        assert_eq!(err.data.unwrap()["parameter"], "domain");
+
+        let err = parse_params::<Params>(serde_json::json!({ "domain": "a", "spaces": 1 }), FIELDS)
+            .unwrap_err();
+        assert!(matches!(err.ty, ErrorType::InvalidParameters));
+
+        let err =
+            parse_params::<Params>(serde_json::json!(["a", null, "big"]), FIELDS).unwrap_err();
+        assert!(matches!(err.ty, ErrorType::InvalidParameters));
+        assert_eq!(err.data.unwrap()["parameter"], "space");
+
+        let err = parse_params::<Params>(serde_json::json!(["a", null, 1, 2]), FIELDS).unwrap_err();
+        assert!(matches!(err.ty, ErrorType::InvalidParameters));
+
+        let err = parse_params::<Params>(
+            serde_json::json!({ "domain": "a", "buyer_token_account": "b", "buyerTokenAccount": "c" }),
+            FIELDS,
+        )
+        .unwrap_err();
+        assert_eq!(err.data.unwrap()["reason"], "duplicate parameter");
+    }
+
+    #[test]
+    fn test_nested_keys() {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase", deny_unknown_fields)]
+        struct Nested {
+            domain: String,
+            content: serde_json::Map<String, Value>,
+        }
+        let params: Nested = parse_params(
+            serde_json::json!({ "domain": "bonfida", "content": { "snake_key": null } }),
+            &["domain", "content"],
+        )
+        .unwrap();
+        assert_eq!(params.domain, "bonfida");
+        assert_eq!(params.content["snake_key"], Value::Null);
+    }
+
+    #[test]
+    fn test_to_camel_case() {
+        assert_eq!(to_camel_case("buyer_token_account"), "buyerTokenAccount");
+        assert_eq!(to_camel_case("buyerTokenAccount"), "buyerTokenAccount");
+        assert_eq!(to_camel_case("domain"), "domain");
+    }
+}
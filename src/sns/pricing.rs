@@ -0,0 +1,180 @@
+use pyth_sdk_solana::{state::load_price_account, PriceStatus, VALID_SLOT_PERIOD};
+use solana_sdk::{pubkey, pubkey::Pubkey};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{config::CONFIG, trace, ErrorType};
+
+pub const MAX_LABEL_LENGTH: usize = 32;
+
+pub const MAX_CONFIDENCE_RATIO: f64 = 0.02;
+
+pub const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+pub struct SupportedMint {
+    pub mint: Pubkey,
+    pub decimals: u8,
+}
+
+pub const SUPPORTED_MINTS: [SupportedMint; 6] = [
+    SupportedMint {
+        mint: USDC_MINT,
+        decimals: 6,
+    },
+    // Wrapped SOL
+    SupportedMint {
+        mint: pubkey!("So11111111111111111111111111111111111111112"),
+        decimals: 9,
+    },
+    // USDT
+    SupportedMint {
+        mint: pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB"),
+        decimals: 6,
+    },
+    // BONK
+    SupportedMint {
+        mint: pubkey!("DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263"),
+        decimals: 5,
+    },
+    // mSOL
+    SupportedMint {
+        mint: pubkey!("mSoLzYCxHdYgdzU16g5QSh3i5K3z3KZK7ytfqcJm7So"),
+        decimals: 9,
+    },
+    // FIDA
+    SupportedMint {
+        mint: pubkey!("EchesyfXePKdLtoiZSL8pBe8Myagyy8ZRqsACNCFGnvp"),
+        decimals: 6,
+    },
+];
+
+pub fn get_supported_mint(mint: &Pubkey) -> Option<&'static SupportedMint> {
+    SUPPORTED_MINTS.iter().find(|m| &m.mint == mint)
+}
+
+pub fn get_price_feed(mint: &Pubkey) -> Option<Pubkey> {
+    CONFIG
+        .price_feeds
+        .iter()
+        .find(|(m, _)| m == mint)
+        .map(|(_, feed)| *feed)
+}
+
+pub fn validate_label(label: &str) -> Result<usize, crate::Error> {
+    if label.is_empty() {
+        return Err(trace!(ErrorType::InvalidDomain, "Empty label"));
+    }
+    if label.contains('.') {
+        return Err(trace!(
+            ErrorType::InvalidDomain,
+            "Subdomains cannot be registered"
+        ));
+    }
+    if label.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(trace!(
+            ErrorType::InvalidDomain,
+            "Label contains whitespace or control characters"
+        ));
+    }
+    if label.chars().any(|c| c.is_uppercase()) {
+        return Err(trace!(ErrorType::InvalidDomain, "Label must be lowercase"));
+    }
+    let length = label.graphemes(true).count();
+    if length > MAX_LABEL_LENGTH {
+        return Err(trace!(ErrorType::InvalidDomain, "Label is too long"));
+    }
+    Ok(length)
+}
+
+/// `usd_prices` holds the price of each label length, the last one applies to all longer labels
+pub fn get_usd_price(usd_prices: &[u64], length: usize) -> u64 {
+    usd_prices
+        .get(length.saturating_sub(1))
+        .or(usd_prices.last())
+        .copied()
+        .unwrap_or_default()
+}
+
+/// Aggregate price of a Pyth price account, rejected when it was not published in the last
+/// `VALID_SLOT_PERIOD` slots and `max_age` seconds, or when its confidence interval is wider than
+/// `MAX_CONFIDENCE_RATIO` of the price
+pub fn parse_pyth_price(
+    data: &[u8],
+    current_slot: u64,
+    current_time: i64,
+    max_age: u64,
+) -> Result<f64, crate::Error> {
+    let account = load_price_account(data).map_err(|e| trace!(ErrorType::Generic, e))?;
+    let price = account.agg.price;
+    if !matches!(account.agg.status, PriceStatus::Trading) || price <= 0 {
+        return Err(trace!(ErrorType::Generic, "Price feed is unavailable"));
+    }
+    if current_slot.saturating_sub(account.agg.pub_slot) > VALID_SLOT_PERIOD
+        || current_time.saturating_sub(account.timestamp) > max_age as i64
+    {
+        return Err(trace!(ErrorType::Generic, "Price feed is stale"));
+    }
+    if account.agg.conf as f64 > price as f64 * MAX_CONFIDENCE_RATIO {
+        return Err(trace!(ErrorType::Generic, "Price feed is too uncertain"));
+    }
+    Ok(price as f64 * 10f64.powi(account.expo))
+}
+
+pub fn get_token_amount(usd_price: u64, token_price: f64, decimals: u8) -> u64 {
+    (usd_price as f64 / token_price * 10f64.powi(decimals as i32)).ceil() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_label() {
+        assert_eq!(validate_label("bonfida").unwrap(), 7);
+        assert_eq!(validate_label("👨‍🌾").unwrap(), 1);
+        assert!(validate_label("").is_err());
+        assert!(validate_label("dex.bonfida").is_err());
+        assert!(validate_label("Bonfida").is_err());
+        assert!(validate_label("bon fida").is_err());
+        assert!(validate_label(&"a".repeat(MAX_LABEL_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn test_usd_price() {
+        let usd_prices = [750, 700, 640, 160, 20];
+        assert_eq!(get_usd_price(&usd_prices, 1), 750);
+        assert_eq!(get_usd_price(&usd_prices, 4), 160);
+        assert_eq!(get_usd_price(&usd_prices, 5), 20);
+        assert_eq!(get_usd_price(&usd_prices, 32), 20);
+    }
+
+    #[test]
+    fn test_pyth_price() {
+        use pyth_sdk_solana::state::{AccountType, PriceAccount, MAGIC, VERSION_2};
+        let slot = 1_000;
+        let time = 1_700_000_000;
+        let mut account: PriceAccount = bytemuck::Zeroable::zeroed();
+        account.magic = MAGIC;
+        account.ver = VERSION_2;
+        account.atype = AccountType::Price as u32;
+        account.expo = -8;
+        account.timestamp = time - 10;
+        account.agg.price = 2_000_000_000;
+        account.agg.conf = 1_000_000;
+        account.agg.status = PriceStatus::Trading;
+        account.agg.pub_slot = slot - 5;
+        let data = |account: &PriceAccount| bytemuck::bytes_of(account).to_vec();
+
+        let price = parse_pyth_price(&data(&account), slot, time, 60).unwrap();
+        assert!((price - 20.0).abs() < 1e-9);
+        assert_eq!(get_token_amount(20, price, 9), 1_000_000_000);
+        // Stale publish slot or time
+        assert!(parse_pyth_price(&data(&account), slot + VALID_SLOT_PERIOD, time, 60).is_err());
+        assert!(parse_pyth_price(&data(&account), slot, time + 60, 60).is_err());
+        // Wide confidence interval
+        account.agg.conf = 100_000_000;
+        assert!(parse_pyth_price(&data(&account), slot, time, 60).is_err());
+        account.agg.conf = 1_000_000;
+        account.agg.status = PriceStatus::Halted;
+        assert!(parse_pyth_price(&data(&account), slot, time, 60).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+use std::str::FromStr;
+
+use sns_sdk::{
+    derivation::get_domain_key,
+    record::{Record, RecordVersion, CENTRAL_STATE_RECORD_V2},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{pubkey::Pubkey, system_program};
+
+use crate::{append_trace, trace, ErrorType};
+
+use super::{
+    get_record_key_from_domain_key, tokenization::get_nft_record_key, transaction::get_name_owner,
+};
+
+/// Request parameters, validated before the domain owner is fetched
+pub struct RecordV2Target {
+    pub record: Record,
+    pub domain_key: Pubkey,
+    pub record_key: Pubkey,
+    fee_payer: Option<Pubkey>,
+}
+
+impl RecordV2Target {
+    pub fn new(domain: &str, record: &str, fee_payer: Option<&str>) -> Result<Self, crate::Error> {
+        let record =
+            Record::try_from_str(record).map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+        let domain_key = get_domain_key(domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+        let record_key = get_record_key_from_domain_key(&domain_key, record, RecordVersion::V2);
+        let fee_payer = fee_payer
+            .map(Pubkey::from_str)
+            .transpose()
+            .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+        Ok(Self {
+            record,
+            domain_key,
+            record_key,
+            fee_payer,
+        })
+    }
+}
+
+pub struct RecordV2Context {
+    pub record: Record,
+    pub domain_key: Pubkey,
+    pub record_key: Pubkey,
+    pub domain_owner: Pubkey,
+    pub fee_payer: Pubkey,
+    pub central_state: Pubkey,
+    pub system_program: Pubkey,
+    pub spl_name_service_program: Pubkey,
+}
+
+impl RecordV2Context {
+    pub async fn new(rpc_client: &RpcClient, target: RecordV2Target) -> Result<Self, crate::Error> {
+        let domain_owner = get_name_owner(rpc_client, &target.domain_key)
+            .await
+            .map_err(|e| append_trace!(e))?;
+        // The records program requires the signature of the name account owner, which is a PDA
+        // of the name tokenizer for tokenized domains
+        if domain_owner == get_nft_record_key(&target.domain_key) {
+            return Err(trace!(
+                ErrorType::InvalidDomain,
+                "Records of a tokenized domain can only be managed once the NFT is redeemed"
+            ));
+        }
+        Ok(Self {
+            record: target.record,
+            domain_key: target.domain_key,
+            record_key: target.record_key,
+            domain_owner,
+            fee_payer: target.fee_payer.unwrap_or(domain_owner),
+            central_state: CENTRAL_STATE_RECORD_V2,
+            system_program: system_program::ID,
+            spl_name_service_program: spl_name_service::id(),
+        })
+    }
+
+    pub fn record_name(&self) -> String {
+        format!("\x02{}", self.record.as_str())
+    }
+}
@@ -4,21 +4,22 @@ use serde_json::Value;
 use sns_sdk::non_blocking::resolve;
 use solana_client::nonblocking::rpc_client::RpcClient;
 
-use super::get_string_from_value_array;
+use super::{
+    domain_name::{with_normalized_domain, DomainName},
+    params::parse_params,
+};
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
-    domain: String,
+    domain: DomainName,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domain"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
@@ -28,7 +29,8 @@ pub async fn process(rpc_client: RpcClient, params: Value) -> Result<Value, crat
         .await
         .map_err(|e| trace!(ErrorType::Generic, e))?
         .map(|s| s.to_string());
-    Ok(serde_json::to_value(resolved).map_err(|e| trace!(ErrorType::Generic, e)))?
+    let value = serde_json::to_value(resolved).map_err(|e| trace!(ErrorType::Generic, e))?;
+    with_normalized_domain(&params.domain, value)
 }
 
 #[cfg(test)]
@@ -50,7 +52,7 @@ mod tests {
         eprintln!("{:#?}", response);
         if response.status().is_success() {
             let result: RpcResponseOk<String> = response.json().await.unwrap();
-            let value = result.result.as_str().unwrap();
+            let value = result.result["value"].as_str().unwrap();
             assert_eq!(value, "HKKp49qGWXd639QsuH7JiLijfVW5UtCVY4s1n2HANwEA");
         } else {
             let text = response.text().await.unwrap();
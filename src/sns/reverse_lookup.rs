@@ -7,21 +7,19 @@ use sns_sdk::non_blocking::resolve;
 use solana_client::nonblocking::rpc_client::RpcClient;
 use solana_sdk::pubkey::Pubkey;
 
-use super::get_string_from_value_array;
+use super::params::parse_params;
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct Params {
     domain_key: String,
 }
 
 impl Params {
+    const FIELDS: &'static [&'static str] = &["domainKey"];
+
     pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
-        if let Some(v) = value.as_array() {
-            let domain_key = get_string_from_value_array(v, 0).map_err(|e| append_trace!(e))?;
-            Ok(Self { domain_key })
-        } else {
-            serde_json::from_value(value).map_err(|e| trace!(ErrorType::InvalidParameters, e))
-        }
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
     }
 }
 
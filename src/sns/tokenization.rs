@@ -0,0 +1,282 @@
+use std::str::FromStr;
+
+use futures::{stream, StreamExt};
+use mpl_token_metadata::accounts::Metadata;
+use sns_sdk::derivation::{get_domain_key, get_domain_mint, NAME_TOKENIZER_ID};
+use solana_account_decoder::UiAccountData;
+use solana_client::{nonblocking::rpc_client::RpcClient, rpc_request::TokenAccountsFilter};
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
+
+use crate::{append_trace, trace, ErrorType};
+
+use super::get_multiple_accounts_batched;
+
+const MAX_CONCURRENT_REQUESTS: usize = 8;
+const NFT_RECORD_ACTIVE_TAG: u8 = 2;
+
+pub fn get_nft_record_key(name_account: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"nft_record", name_account.as_ref()], &NAME_TOKENIZER_ID).0
+}
+
+pub struct DomainOwner {
+    pub owner: Pubkey,
+    pub tokenized: bool,
+}
+
+pub async fn get_domain_owners(
+    rpc_client: &RpcClient,
+    name_accounts: &[Pubkey],
+) -> Result<Vec<Option<DomainOwner>>, crate::Error> {
+    let owners = get_multiple_accounts_batched(rpc_client, name_accounts)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter()
+        .map(|a| a.as_ref().map(parse_owner).transpose())
+        .collect::<Result<Vec<_>, _>>()?;
+    let tokenized = name_accounts
+        .iter()
+        .zip(owners.iter())
+        .filter(|(k, o)| **o == Some(get_nft_record_key(k)))
+        .map(|(k, _)| *k)
+        .collect::<Vec<_>>();
+    let mut holders = get_nft_holders(rpc_client, &tokenized)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter();
+    let mut result = Vec::with_capacity(name_accounts.len());
+    for (name_account, owner) in name_accounts.iter().zip(owners) {
+        let owner = match owner {
+            None => None,
+            Some(owner) if owner == get_nft_record_key(name_account) => holders
+                .next()
+                .ok_or(trace!(ErrorType::Generic))?
+                .map(|owner| DomainOwner {
+                    owner,
+                    tokenized: true,
+                }),
+            Some(owner) => Some(DomainOwner {
+                owner,
+                tokenized: false,
+            }),
+        };
+        result.push(owner);
+    }
+    Ok(result)
+}
+
+pub async fn get_nft_holder(
+    rpc_client: &RpcClient,
+    name_account: &Pubkey,
+) -> Result<Option<Pubkey>, crate::Error> {
+    let holders = get_nft_holders(rpc_client, &[*name_account])
+        .await
+        .map_err(|e| append_trace!(e))?;
+    Ok(holders.into_iter().next().flatten())
+}
+
+/// NFT holders of tokenized domains.
+///
+/// The NFT records and the holder token accounts are each fetched with batched
+/// `getMultipleAccounts`, `getTokenLargestAccounts` has no batched form so those requests are
+/// sent concurrently.
+pub async fn get_nft_holders(
+    rpc_client: &RpcClient,
+    name_accounts: &[Pubkey],
+) -> Result<Vec<Option<Pubkey>>, crate::Error> {
+    let record_keys = name_accounts
+        .iter()
+        .map(get_nft_record_key)
+        .collect::<Vec<_>>();
+    let mints = get_multiple_accounts_batched(rpc_client, &record_keys)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter()
+        .map(|a| a.and_then(|a| parse_active_nft_mint(&a.data)))
+        .collect::<Vec<_>>();
+    let holder_accounts = stream::iter(
+        mints
+            .into_iter()
+            .map(|mint| get_holder_account(rpc_client, mint)),
+    )
+    .buffered(MAX_CONCURRENT_REQUESTS)
+    .collect::<Vec<_>>()
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+    let keys = holder_accounts
+        .iter()
+        .flatten()
+        .copied()
+        .collect::<Vec<_>>();
+    let mut accounts = get_multiple_accounts_batched(rpc_client, &keys)
+        .await
+        .map_err(|e| append_trace!(e))?
+        .into_iter();
+    let mut result = Vec::with_capacity(name_accounts.len());
+    for holder_account in holder_accounts {
+        let holder = match holder_account {
+            Some(_) => accounts
+                .next()
+                .ok_or(trace!(ErrorType::Generic))?
+                .map(|a| spl_token::state::Account::unpack(&a.data))
+                .transpose()
+                .map_err(|e| trace!(ErrorType::Generic, e))?
+                .map(|a| a.owner),
+            None => None,
+        };
+        result.push(holder);
+    }
+    Ok(result)
+}
+
+async fn get_holder_account(
+    rpc_client: &RpcClient,
+    mint: Option<Pubkey>,
+) -> Result<Option<Pubkey>, crate::Error> {
+    let Some(mint) = mint else {
+        return Ok(None);
+    };
+    let largest_accounts = rpc_client
+        .get_token_largest_accounts(&mint)
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+    largest_accounts
+        .iter()
+        .find(|a| a.amount.amount == "1")
+        .map(|a| Pubkey::from_str(&a.address))
+        .transpose()
+        .map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+// NftRecord layout: tag (1) | nonce (1) | name_account (32) | owner (32) | nft_mint (32)
+fn parse_active_nft_mint(data: &[u8]) -> Option<Pubkey> {
+    if data.first() != Some(&NFT_RECORD_ACTIVE_TAG) {
+        return None;
+    }
+    data.get(66..98).and_then(|b| Pubkey::try_from(b).ok())
+}
+
+fn parse_owner(account: &Account) -> Result<Pubkey, crate::Error> {
+    let header = NameRecordHeader::unpack_unchecked(&account.data[..NameRecordHeader::LEN])
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    Ok(header.owner)
+}
+
+/// Tokenized domains held by `owner`.
+///
+/// The NFT metadata name is checked against the domain key by the name tokenizer, so the
+/// domain is recovered from the metadata of each candidate mint with one batched
+/// `getMultipleAccounts`, and kept only if its derived mint matches.
+pub async fn get_tokenized_domains(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+) -> Result<Vec<Pubkey>, crate::Error> {
+    let token_accounts = rpc_client
+        .get_token_accounts_by_owner(owner, TokenAccountsFilter::ProgramId(spl_token::id()))
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+    let mints = token_accounts
+        .iter()
+        .filter_map(|a| parse_nft_mint(&a.account.data))
+        .collect::<Vec<_>>();
+    let metadata_keys = mints
+        .iter()
+        .map(|mint| Metadata::find_pda(mint).0)
+        .collect::<Vec<_>>();
+    let metadata_accounts = get_multiple_accounts_batched(rpc_client, &metadata_keys)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let name_accounts = mints
+        .iter()
+        .zip(metadata_accounts)
+        .filter_map(|(mint, account)| get_name_account_from_metadata(mint, &account?.data))
+        .collect();
+    Ok(name_accounts)
+}
+
+/// Domain tokenized as `mint`, recovered from the NFT metadata like in `get_tokenized_domains`
+pub async fn get_tokenized_domain(
+    rpc_client: &RpcClient,
+    mint: &Pubkey,
+) -> Result<Option<Pubkey>, crate::Error> {
+    let metadata_key = Metadata::find_pda(mint).0;
+    let account = rpc_client
+        .get_account_with_commitment(&metadata_key, rpc_client.commitment())
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .value;
+    Ok(account.and_then(|a| get_name_account_from_metadata(mint, &a.data)))
+}
+
+fn get_name_account_from_metadata(mint: &Pubkey, data: &[u8]) -> Option<Pubkey> {
+    let metadata = Metadata::from_bytes(data).ok()?;
+    if metadata.mint != *mint {
+        return None;
+    }
+    let name = metadata.name.trim_end_matches('\0');
+    let name_account = get_domain_key(name).ok()?;
+    (get_domain_mint(&name_account) == *mint).then_some(name_account)
+}
+
+fn parse_nft_mint(data: &UiAccountData) -> Option<Pubkey> {
+    let UiAccountData::Json(account) = data else {
+        return None;
+    };
+    let info = account.parsed.get("info")?;
+    let amount = info.get("tokenAmount")?;
+    if amount.get("amount")?.as_str()? != "1" || amount.get("decimals")?.as_u64()? != 0 {
+        return None;
+    }
+    Pubkey::from_str(info.get("mint")?.as_str()?).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Metadata layout: key (1) | update_authority (32) | mint (32) | name | symbol | uri | ...
+    fn metadata_data(mint: &Pubkey, name: &str) -> Vec<u8> {
+        let mut data = vec![4];
+        data.extend_from_slice(&NAME_TOKENIZER_ID.to_bytes());
+        data.extend_from_slice(&mint.to_bytes());
+        for field in [name, "", ""] {
+            data.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            data.extend_from_slice(field.as_bytes());
+        }
+        data.resize(679, 0);
+        data
+    }
+
+    #[test]
+    fn test_parse_active_nft_mint() {
+        let name_account = get_domain_key("bonfida").unwrap();
+        let mint = get_domain_mint(&name_account);
+        let mut data = vec![NFT_RECORD_ACTIVE_TAG, 255];
+        data.extend_from_slice(&name_account.to_bytes());
+        data.extend_from_slice(&Pubkey::new_unique().to_bytes());
+        data.extend_from_slice(&mint.to_bytes());
+        assert_eq!(parse_active_nft_mint(&data), Some(mint));
+        // Inactive records belong to domains that were withdrawn from their NFT
+        data[0] = NFT_RECORD_ACTIVE_TAG + 1;
+        assert_eq!(parse_active_nft_mint(&data), None);
+        assert_eq!(parse_active_nft_mint(&data[..66]), None);
+    }
+
+    #[test]
+    fn test_name_account_from_metadata() {
+        let name_account = get_domain_key("bonfida").unwrap();
+        let mint = get_domain_mint(&name_account);
+        let data = metadata_data(&mint, "bonfida\0\0\0");
+        assert_eq!(
+            get_name_account_from_metadata(&mint, &data),
+            Some(name_account)
+        );
+        // The metadata name must derive the mint it belongs to
+        let data = metadata_data(&mint, "solana");
+        assert_eq!(get_name_account_from_metadata(&mint, &data), None);
+        let other_mint = Pubkey::new_unique();
+        let data = metadata_data(&other_mint, "bonfida");
+        assert_eq!(get_name_account_from_metadata(&other_mint, &data), None);
+    }
+}
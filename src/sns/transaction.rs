@@ -0,0 +1,453 @@
+use std::str::FromStr;
+
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::{
+    nonblocking::rpc_client::RpcClient, rpc_config::RpcSimulateTransactionConfig,
+    rpc_response::RpcSimulateTransactionResult,
+};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    compute_budget::ComputeBudgetInstruction,
+    instruction::InstructionError,
+    instruction::{AccountMeta, Instruction},
+    message::{v0, Message, VersionedMessage},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction},
+};
+use spl_name_service::state::NameRecordHeader;
+
+use crate::{append_trace, trace, ErrorType};
+
+use super::get_multiple_accounts_batched;
+
+// AddressLookupTable layout: 56 bytes of metadata followed by the addresses
+const LOOKUP_TABLE_META_SIZE: usize = 56;
+const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TransactionOptions {
+    pub priority_fee: Option<PriorityFee>,
+    pub compute_unit_limit: Option<u32>,
+    #[serde(default)]
+    pub format: TransactionFormat,
+    #[serde(default)]
+    pub lookup_tables: Vec<String>,
+    pub fee_payer: Option<String>,
+    #[serde(default)]
+    pub simulate: bool,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+pub struct SimulatedTransaction {
+    pub transaction: Value,
+    pub simulation: Simulation,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct Simulation {
+    pub logs: Vec<String>,
+    pub units_consumed: Option<u64>,
+    pub err: Option<SimulationError>,
+}
+
+#[derive(Serialize)]
+#[cfg_attr(test, derive(Deserialize))]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationError {
+    pub message: String,
+    pub instruction_index: Option<u8>,
+    pub program_id: Option<String>,
+    pub custom_code: Option<u32>,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(untagged)]
+pub enum PriorityFee {
+    MicroLamports(u64),
+    Mode(PriorityFeeMode),
+}
+
+#[derive(Deserialize, Clone, Copy)]
+#[serde(rename_all = "camelCase")]
+pub enum PriorityFeeMode {
+    Auto,
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum TransactionFormat {
+    #[default]
+    Legacy,
+    V0,
+}
+
+impl TransactionOptions {
+    /// Builders that predate the options object took the fee payer as a top-level `payer`
+    /// parameter, which is kept as an alias of `feePayer`
+    pub fn with_payer(mut self, payer: Option<String>) -> Result<Self, crate::Error> {
+        if payer.is_some() {
+            if self.fee_payer.is_some() {
+                return Err(trace!(
+                    ErrorType::InvalidParameters,
+                    "payer and options.feePayer are mutually exclusive"
+                ));
+            }
+            self.fee_payer = payer;
+        }
+        Ok(self)
+    }
+
+    pub fn fee_payer(&self, default: &Pubkey) -> Result<Pubkey, crate::Error> {
+        match &self.fee_payer {
+            Some(p) => Pubkey::from_str(p).map_err(|e| trace!(ErrorType::InvalidParameters, e)),
+            None => Ok(*default),
+        }
+    }
+}
+
+pub async fn build_transaction(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+    default_payer: &Pubkey,
+    options: &TransactionOptions,
+) -> Result<VersionedTransaction, crate::Error> {
+    let payer = options
+        .fee_payer(default_payer)
+        .map_err(|e| append_trace!(e))?;
+    let mut all_instructions = Vec::with_capacity(instructions.len() + 2);
+    if let Some(limit) = options.compute_unit_limit {
+        if limit == 0 || limit > MAX_COMPUTE_UNIT_LIMIT {
+            return Err(trace!(ErrorType::InvalidParameters, limit));
+        }
+        all_instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(limit));
+    }
+    if let Some(priority_fee) = options.priority_fee {
+        let micro_lamports = match priority_fee {
+            PriorityFee::MicroLamports(m) => m,
+            PriorityFee::Mode(PriorityFeeMode::Auto) => {
+                get_auto_priority_fee(rpc_client, instructions)
+                    .await
+                    .map_err(|e| append_trace!(e))?
+            }
+        };
+        all_instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+            micro_lamports,
+        ));
+    }
+    all_instructions.extend_from_slice(instructions);
+
+    let blockhash = rpc_client
+        .get_latest_blockhash()
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+    let message = match options.format {
+        TransactionFormat::Legacy => {
+            if !options.lookup_tables.is_empty() {
+                return Err(trace!(
+                    ErrorType::InvalidParameters,
+                    "Lookup tables require the v0 format"
+                ));
+            }
+            VersionedMessage::Legacy(Message::new_with_blockhash(
+                &all_instructions,
+                Some(&payer),
+                &blockhash,
+            ))
+        }
+        TransactionFormat::V0 => {
+            let lookup_tables = get_lookup_tables(rpc_client, &options.lookup_tables)
+                .await
+                .map_err(|e| append_trace!(e))?;
+            let message =
+                v0::Message::try_compile(&payer, &all_instructions, &lookup_tables, blockhash)
+                    .map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+            VersionedMessage::V0(message)
+        }
+    };
+    let signatures = vec![Signature::default(); message.header().num_required_signatures as usize];
+    Ok(VersionedTransaction {
+        signatures,
+        message,
+    })
+}
+
+// Picks the median of the recent fees paid to write-lock the accounts of the transaction
+async fn get_auto_priority_fee(
+    rpc_client: &RpcClient,
+    instructions: &[Instruction],
+) -> Result<u64, crate::Error> {
+    let mut writable_accounts = instructions
+        .iter()
+        .flat_map(|i| i.accounts.iter())
+        .filter(|a| a.is_writable)
+        .map(|a| a.pubkey)
+        .collect::<Vec<_>>();
+    writable_accounts.sort();
+    writable_accounts.dedup();
+    let mut fees = rpc_client
+        .get_recent_prioritization_fees(&writable_accounts)
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .into_iter()
+        .map(|f| f.prioritization_fee)
+        .collect::<Vec<_>>();
+    if fees.is_empty() {
+        return Ok(0);
+    }
+    fees.sort_unstable();
+    Ok(fees[fees.len() / 2])
+}
+
+async fn get_lookup_tables(
+    rpc_client: &RpcClient,
+    keys: &[String],
+) -> Result<Vec<AddressLookupTableAccount>, crate::Error> {
+    let keys = keys
+        .iter()
+        .map(|k| Pubkey::from_str(k).map_err(|e| trace!(ErrorType::InvalidParameters, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let accounts = get_multiple_accounts_batched(rpc_client, &keys)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    keys.into_iter()
+        .zip(accounts)
+        .map(|(key, account)| {
+            let account = account.ok_or(trace!(ErrorType::InvalidParameters, key))?;
+            let addresses = account
+                .data
+                .get(LOOKUP_TABLE_META_SIZE..)
+                .ok_or(trace!(ErrorType::InvalidParameters, key))?
+                .chunks_exact(32)
+                .map(|c| Pubkey::try_from(c).map_err(|e| trace!(ErrorType::Generic, e)))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(AddressLookupTableAccount { key, addresses })
+        })
+        .collect()
+}
+
+pub fn decompile_instructions(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: message.account_keys[ix.program_id_index as usize],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|&i| {
+                    let i = i as usize;
+                    AccountMeta {
+                        pubkey: message.account_keys[i],
+                        is_signer: message.is_signer(i),
+                        is_writable: message.is_writable(i),
+                    }
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
+pub async fn get_transaction_result(
+    rpc_client: &RpcClient,
+    transaction: &VersionedTransaction,
+    options: &TransactionOptions,
+) -> Result<Value, crate::Error> {
+    let encoded_transaction = encode_transaction(transaction).map_err(|e| append_trace!(e))?;
+    if !options.simulate {
+        return Ok(encoded_transaction);
+    }
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        commitment: Some(rpc_client.commitment()),
+        ..RpcSimulateTransactionConfig::default()
+    };
+    let result = rpc_client
+        .simulate_transaction_with_config(transaction, config)
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .value;
+    let result = SimulatedTransaction {
+        transaction: encoded_transaction,
+        simulation: parse_simulation(transaction, result),
+    };
+    serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+fn parse_simulation(
+    transaction: &VersionedTransaction,
+    result: RpcSimulateTransactionResult,
+) -> Simulation {
+    let err = result.err.map(|err| {
+        let mut error = SimulationError {
+            message: err.to_string(),
+            instruction_index: None,
+            program_id: None,
+            custom_code: None,
+        };
+        if let TransactionError::InstructionError(index, instruction_error) = &err {
+            error.instruction_index = Some(*index);
+            error.program_id = transaction
+                .message
+                .instructions()
+                .get(*index as usize)
+                .and_then(|ix| {
+                    transaction
+                        .message
+                        .static_account_keys()
+                        .get(ix.program_id_index as usize)
+                })
+                .map(|k| k.to_string());
+            if let InstructionError::Custom(code) = instruction_error {
+                error.custom_code = Some(*code);
+            }
+        }
+        error
+    });
+    Simulation {
+        logs: result.logs.unwrap_or_default(),
+        units_consumed: result.units_consumed,
+        err,
+    }
+}
+
+fn encode_transaction(transaction: &VersionedTransaction) -> Result<Value, crate::Error> {
+    let serialized_transaction =
+        bincode::serialize(transaction).map_err(|e| trace!(ErrorType::Generic, e))?;
+    let encoded_transaction =
+        base64::engine::general_purpose::STANDARD.encode(serialized_transaction);
+    serde_json::to_value(encoded_transaction).map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+pub async fn get_name_owner(
+    rpc_client: &RpcClient,
+    name_account: &Pubkey,
+) -> Result<Pubkey, crate::Error> {
+    let header = get_name_header(rpc_client, name_account)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    Ok(header.owner)
+}
+
+pub async fn get_name_header(
+    rpc_client: &RpcClient,
+    name_account: &Pubkey,
+) -> Result<NameRecordHeader, crate::Error> {
+    let account = rpc_client
+        .get_account_with_commitment(name_account, rpc_client.commitment())
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .value
+        .ok_or(trace!(ErrorType::DomainNotFound, name_account))?;
+    NameRecordHeader::unpack_unchecked(&account.data[..NameRecordHeader::LEN])
+        .map_err(|e| trace!(ErrorType::Generic, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_deserialization() {
+        let options: TransactionOptions = serde_json::from_value(serde_json::json!({
+            "priorityFee": "auto",
+            "computeUnitLimit": 200_000,
+            "format": "v0",
+        }))
+        .unwrap();
+        assert!(matches!(
+            options.priority_fee,
+            Some(PriorityFee::Mode(PriorityFeeMode::Auto))
+        ));
+        assert!(options.format == TransactionFormat::V0);
+        let options: TransactionOptions =
+            serde_json::from_value(serde_json::json!({ "priorityFee": 1000 })).unwrap();
+        assert!(matches!(
+            options.priority_fee,
+            Some(PriorityFee::MicroLamports(1000))
+        ));
+        assert!(options.format == TransactionFormat::Legacy);
+    }
+
+    #[test]
+    fn test_parse_simulation() {
+        let payer = Pubkey::new_unique();
+        let program_id = Pubkey::new_unique();
+        let instruction = Instruction {
+            program_id,
+            accounts: vec![AccountMeta::new(payer, true)],
+            data: vec![],
+        };
+        let message = Message::new(&[instruction], Some(&payer));
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::Legacy(message),
+        };
+        let result = RpcSimulateTransactionResult {
+            err: Some(TransactionError::InstructionError(
+                0,
+                InstructionError::Custom(1),
+            )),
+            logs: Some(vec!["Program log: insufficient funds".to_owned()]),
+            accounts: None,
+            units_consumed: Some(1_000),
+            return_data: None,
+            inner_instructions: None,
+        };
+        let simulation = parse_simulation(&transaction, result);
+        let err = simulation.err.unwrap();
+        assert_eq!(err.instruction_index, Some(0));
+        assert_eq!(err.program_id, Some(program_id.to_string()));
+        assert_eq!(err.custom_code, Some(1));
+        assert_eq!(simulation.units_consumed, Some(1_000));
+        assert_eq!(simulation.logs.len(), 1);
+    }
+
+    #[test]
+    fn test_decompile_instructions() {
+        let payer = Pubkey::new_unique();
+        let instruction = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: vec![
+                AccountMeta::new(Pubkey::new_unique(), false),
+                AccountMeta::new_readonly(payer, true),
+            ],
+            data: vec![1, 2, 3],
+        };
+        let message = Message::new(std::slice::from_ref(&instruction), Some(&payer));
+        let decompiled = decompile_instructions(&message);
+        assert_eq!(decompiled.len(), 1);
+        assert_eq!(decompiled[0].program_id, instruction.program_id);
+        assert_eq!(decompiled[0].data, instruction.data);
+        assert_eq!(decompiled[0].accounts[0], instruction.accounts[0]);
+        // The fee payer is always writable
+        assert_eq!(decompiled[0].accounts[1].pubkey, payer);
+        assert!(decompiled[0].accounts[1].is_signer);
+        assert!(decompiled[0].accounts[1].is_writable);
+    }
+
+    #[test]
+    fn test_with_payer() {
+        let payer = Pubkey::new_unique().to_string();
+        let options = TransactionOptions::default()
+            .with_payer(Some(payer.clone()))
+            .unwrap();
+        assert_eq!(options.fee_payer.as_deref(), Some(payer.as_str()));
+        let options = TransactionOptions::default().with_payer(None).unwrap();
+        assert!(options.fee_payer.is_none());
+        assert!(options.with_payer(Some(payer.clone())).is_ok());
+        let options = TransactionOptions {
+            fee_payer: Some(payer.clone()),
+            ..Default::default()
+        };
+        assert!(options.with_payer(Some(payer)).is_err());
+    }
+}
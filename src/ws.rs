@@ -0,0 +1,390 @@
+use std::{collections::HashMap, sync::Arc};
+
+use actix_web::{get, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use futures::{channel::mpsc, stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use solana_client::nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient};
+use solana_sdk::commitment_config::CommitmentConfig;
+
+use crate::{
+    append_trace,
+    db::DbConnector,
+    sns::{get_provisioning_info, params::parse_params, RpcResponseError, RpcResponseOk, JSON_RPC},
+    trace, ErrorType,
+};
+
+use subscription::{get_subscription_limiter, Decode, Subscription, SubscriptionSlot, Target};
+
+pub mod subscribe_domain;
+pub mod subscribe_owner;
+pub mod subscribe_record;
+pub mod subscription;
+
+pub const NOTIFICATION_METHOD: &str = "sns_notification";
+// Notifications waiting to be written to a connection, later ones are dropped
+const NOTIFICATION_BUFFER: usize = 256;
+
+#[derive(Deserialize)]
+pub enum Method {
+    #[serde(rename = "sns_subscribeDomain")]
+    SubscribeDomain,
+    #[serde(rename = "sns_subscribeRecord")]
+    SubscribeRecord,
+    #[serde(rename = "sns_subscribeOwner")]
+    SubscribeOwner,
+    #[serde(rename = "sns_unsubscribe")]
+    Unsubscribe,
+    #[serde(other)]
+    Unsupported,
+}
+
+#[derive(Deserialize)]
+pub struct WsMessage {
+    pub jsonrpc: String,
+    pub method: Method,
+    #[serde(default)]
+    pub params: Value,
+    pub id: Value,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct UnsubscribeParams {
+    subscription: u64,
+}
+
+impl UnsubscribeParams {
+    const FIELDS: &'static [&'static str] = &["subscription"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+#[derive(Serialize)]
+pub struct Notification {
+    jsonrpc: &'static str,
+    method: &'static str,
+    params: NotificationParams,
+}
+
+#[derive(Serialize)]
+pub struct NotificationParams {
+    subscription: u64,
+    result: Value,
+}
+
+enum Event {
+    Message(Result<Message, actix_ws::ProtocolError>),
+    Notification((u64, Value)),
+    Closed,
+}
+
+struct Connection {
+    endpoint: String,
+    wss_url: String,
+    /// Opened on the first subscription
+    pubsub_client: Option<Arc<PubsubClient>>,
+    rpc_client: Arc<RpcClient>,
+    subscriptions: HashMap<u64, Subscription>,
+    next_id: u64,
+    notifications: mpsc::Sender<(u64, Value)>,
+    /// Counts the connection towards the endpoint limit
+    _slot: Option<SubscriptionSlot>,
+}
+
+#[get("/ws")]
+pub async fn route(
+    request: HttpRequest,
+    body: web::Payload,
+    db: web::Data<DbConnector>,
+) -> Result<HttpResponse, crate::Error> {
+    let provisioning_info = get_provisioning_info(&db, &request)
+        .await
+        .map_err(|e| append_trace!(e))?;
+    let slot = get_subscription_limiter()
+        .acquire_connection(&provisioning_info.endpoint_id)
+        .map_err(|e| append_trace!(e))?;
+    let (response, session, messages) =
+        actix_ws::handle(&request, body).map_err(|e| trace!(ErrorType::MalformedRequest, e))?;
+    let (connection, receiver) = Connection::new(
+        provisioning_info.endpoint_id,
+        provisioning_info.wss_url,
+        provisioning_info.http_url,
+        Some(slot),
+    );
+    actix_web::rt::spawn(run(connection, session, messages, receiver));
+    Ok(response)
+}
+
+// Subscriptions are cancelled when the connection is dropped
+async fn run(
+    mut connection: Connection,
+    mut session: Session,
+    messages: MessageStream,
+    notifications: mpsc::Receiver<(u64, Value)>,
+) {
+    let mut events = stream::select(
+        messages
+            .map(Event::Message)
+            .chain(stream::iter([Event::Closed])),
+        notifications.map(Event::Notification),
+    );
+    while let Some(event) = events.next().await {
+        // The upstream connection is gone, the next subscription opens a new one
+        if let Event::Notification((_, result)) = &event {
+            if result["type"] == "closed" {
+                connection.pubsub_client = None;
+            }
+        }
+        let result = match event {
+            Event::Message(Ok(Message::Text(text))) => {
+                let response = connection.handle(&text).await;
+                session.text(response).await
+            }
+            Event::Message(Ok(Message::Ping(bytes))) => session.pong(&bytes).await,
+            Event::Message(Ok(Message::Close(reason))) => {
+                let _ = session.close(reason).await;
+                return;
+            }
+            Event::Message(Ok(_)) => Ok(()),
+            Event::Message(Err(e)) => {
+                log::warn!("WebSocket protocol error: {e:?}");
+                break;
+            }
+            // Notifications sent before an unsubscription was processed are dropped
+            Event::Notification((subscription, _))
+                if !connection.subscriptions.contains_key(&subscription) =>
+            {
+                Ok(())
+            }
+            Event::Notification((subscription, result)) => {
+                let notification = Notification {
+                    jsonrpc: JSON_RPC,
+                    method: NOTIFICATION_METHOD,
+                    params: NotificationParams {
+                        subscription,
+                        result,
+                    },
+                };
+                session
+                    .text(serde_json::to_string(&notification).unwrap_or_default())
+                    .await
+            }
+            Event::Closed => break,
+        };
+        if result.is_err() {
+            break;
+        }
+    }
+    let _ = session.close(None).await;
+}
+
+impl Connection {
+    fn new(
+        endpoint: String,
+        wss_url: String,
+        http_url: String,
+        slot: Option<SubscriptionSlot>,
+    ) -> (Self, mpsc::Receiver<(u64, Value)>) {
+        let rpc_client = RpcClient::new_with_commitment(http_url, CommitmentConfig::confirmed());
+        let (notifications, receiver) = mpsc::channel(NOTIFICATION_BUFFER);
+        let connection = Self {
+            endpoint,
+            wss_url,
+            pubsub_client: None,
+            rpc_client: Arc::new(rpc_client),
+            subscriptions: HashMap::new(),
+            next_id: 0,
+            notifications,
+            _slot: slot,
+        };
+        (connection, receiver)
+    }
+
+    async fn pubsub_client(&mut self) -> Result<Arc<PubsubClient>, crate::Error> {
+        if let Some(pubsub_client) = &self.pubsub_client {
+            return Ok(Arc::clone(pubsub_client));
+        }
+        let pubsub_client = PubsubClient::new(&self.wss_url)
+            .await
+            .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?;
+        Ok(Arc::clone(
+            self.pubsub_client.insert(Arc::new(pubsub_client)),
+        ))
+    }
+
+    async fn handle(&mut self, text: &str) -> String {
+        let message = match serde_json::from_str::<WsMessage>(text) {
+            Ok(message) => message,
+            Err(e) => {
+                let error = trace!(ErrorType::MalformedRequest, e);
+                return serde_json::to_string(&RpcResponseError::new(Value::Null, &error))
+                    .unwrap_or_default();
+            }
+        };
+        let id = message.id.clone();
+        let response = match self.process(message).await {
+            Ok(result) => serde_json::to_string(&RpcResponseOk {
+                jsonrpc: JSON_RPC,
+                result,
+                id,
+            }),
+            Err(e) => {
+                log::debug!("WebSocket request failed: {e:?}");
+                serde_json::to_string(&RpcResponseError::new(id, &e))
+            }
+        };
+        response.unwrap_or_default()
+    }
+
+    async fn process(&mut self, message: WsMessage) -> Result<Value, crate::Error> {
+        if message.jsonrpc != JSON_RPC {
+            return Err(trace!(ErrorType::MalformedRequest));
+        }
+        match message.method {
+            Method::SubscribeDomain => {
+                let (targets, decoder) =
+                    subscribe_domain::process(message.params).map_err(|e| append_trace!(e))?;
+                self.subscribe(targets, decoder).await
+            }
+            Method::SubscribeRecord => {
+                let (targets, decoder) =
+                    subscribe_record::process(message.params).map_err(|e| append_trace!(e))?;
+                self.subscribe(targets, decoder).await
+            }
+            Method::SubscribeOwner => {
+                let (targets, decoder) = subscribe_owner::process(&self.rpc_client, message.params)
+                    .await
+                    .map_err(|e| append_trace!(e))?;
+                self.subscribe(targets, decoder).await
+            }
+            Method::Unsubscribe => {
+                let params = UnsubscribeParams::deserialize(message.params)?;
+                let subscription = self.subscriptions.remove(&params.subscription);
+                Ok(Value::Bool(subscription.is_some()))
+            }
+            Method::Unsupported => Err(trace!(ErrorType::UnsupportedEndpoint)),
+        }
+    }
+
+    async fn subscribe<D: Decode + Send + Sync + 'static>(
+        &mut self,
+        targets: Vec<Target>,
+        decoder: D,
+    ) -> Result<Value, crate::Error> {
+        // Every upstream subscription counts towards the endpoint limit
+        let slot = get_subscription_limiter()
+            .acquire_many(&self.endpoint, targets.len())
+            .map_err(|e| append_trace!(e))?;
+        let pubsub_client = self.pubsub_client().await?;
+        let id = self.next_id;
+        let subscription = match subscription::subscribe(
+            &pubsub_client,
+            &self.rpc_client,
+            &self.endpoint,
+            slot,
+            targets,
+            decoder,
+            id,
+            self.notifications.clone(),
+        )
+        .await
+        {
+            Ok(subscription) => subscription,
+            Err(e) => {
+                // A failed upstream subscription may leave the connection unusable
+                if matches!(e.ty, ErrorType::SolanaRpcError) {
+                    self.pubsub_client = None;
+                }
+                return Err(append_trace!(e));
+            }
+        };
+        self.next_id += 1;
+        self.subscriptions.insert(id, subscription);
+        Ok(Value::from(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connection() -> Connection {
+        let (connection, _) = Connection::new(
+            "endpoint".to_owned(),
+            "ws://localhost:8900".to_owned(),
+            "http://localhost:8899".to_owned(),
+            None,
+        );
+        connection
+    }
+
+    async fn handle(connection: &mut Connection, message: Value) -> Value {
+        let response = connection.handle(&message.to_string()).await;
+        serde_json::from_str(&response).unwrap()
+    }
+
+    #[test]
+    fn test_method() {
+        let message: WsMessage = serde_json::from_value(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "sns_subscribeOwner",
+            "id": 1
+        }))
+        .unwrap();
+        assert!(matches!(message.method, Method::SubscribeOwner));
+        assert_eq!(message.params, Value::Null);
+        let method: Method = serde_json::from_value(Value::from("sns_getDomainKey")).unwrap();
+        assert!(matches!(method, Method::Unsupported));
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let mut connection = connection();
+
+        let response = connection.handle("{").await;
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(response["error"]["code"], -32602);
+
+        let message = serde_json::json!({ "jsonrpc": "1.0", "method": "sns_unsubscribe", "id": 1 });
+        let response = handle(&mut connection, message).await;
+        assert_eq!(response["error"]["code"], -32602);
+
+        let message = serde_json::json!({ "jsonrpc": "2.0", "method": "sns_resolve", "id": 2 });
+        let response = handle(&mut connection, message).await;
+        assert_eq!(response["error"]["code"], -32601);
+        assert_eq!(response["id"], 2);
+
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "sns_subscribeDomain",
+            "params": { "owner": "bonfida" },
+            "id": 3
+        });
+        let response = handle(&mut connection, message).await;
+        assert_eq!(response["error"]["code"], -32602);
+
+        let message = serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": "sns_unsubscribe",
+            "params": { "subscription": 0 },
+            "id": 4
+        });
+        let response = handle(&mut connection, message).await;
+        assert_eq!(response["result"], false);
+
+        // Invalid parameters are rejected before the upstream connection is opened
+        assert!(connection.pubsub_client.is_none());
+    }
+
+    #[test]
+    fn test_limit_error_code() {
+        let error = trace!(ErrorType::SubscriptionLimitReached, 100);
+        let response = serde_json::to_value(RpcResponseError::new(Value::from(1), &error)).unwrap();
+        assert_eq!(response["error"]["code"], -32005);
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    append_trace,
+    sns::{
+        domain_name::DomainName,
+        params::parse_params,
+        tokenization::{get_nft_holder, get_nft_record_key},
+    },
+    trace, ErrorType,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sns_sdk::derivation::{get_domain_key, get_domain_mint};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
+
+use super::subscription::{token_accounts_target, Decode, Target, Update};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub struct Decoder {
+    domain_key: Pubkey,
+}
+
+// A transfer of a tokenized domain only changes the token accounts of its NFT, which are watched
+// along with the domain account
+pub fn process(params: Value) -> Result<(Vec<Target>, Decoder), crate::Error> {
+    let params = Params::deserialize(params)?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let mint = get_domain_mint(&domain_key);
+    let targets = vec![
+        Target::Account(domain_key),
+        token_accounts_target(None, Some(&mint)),
+    ];
+    Ok((targets, Decoder { domain_key }))
+}
+
+impl Decode for Decoder {
+    async fn decode(
+        &self,
+        rpc_client: &RpcClient,
+        update: Update,
+    ) -> Result<Option<Value>, crate::Error> {
+        let account = if update.key == self.domain_key {
+            update.account
+        } else {
+            rpc_client
+                .get_account_with_commitment(&self.domain_key, rpc_client.commitment())
+                .await
+                .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+                .value
+        };
+        let Some(account) = account else {
+            return Ok(Some(json!({ "type": "domainDeleted" })));
+        };
+        let header = account
+            .data
+            .get(..NameRecordHeader::LEN)
+            .ok_or(trace!(ErrorType::Generic))?;
+        let header = NameRecordHeader::unpack_unchecked(header)
+            .map_err(|e| trace!(ErrorType::Generic, e))?;
+        let tokenized = header.owner == get_nft_record_key(&self.domain_key);
+        let owner = if tokenized {
+            get_nft_holder(rpc_client, &self.domain_key)
+                .await
+                .map_err(|e| append_trace!(e))?
+        } else {
+            Some(header.owner)
+        };
+        Ok(Some(json!({
+            "type": "owner",
+            "owner": owner.map(|o| o.to_string()),
+            "tokenized": tokenized,
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_decode_deleted() {
+        let (targets, decoder) = process(json!({ "domain": "bonfida" })).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(matches!(targets[0], Target::Account(key) if key == decoder.domain_key));
+        let rpc_client = RpcClient::new("http://localhost:8899".to_owned());
+        let update = Update {
+            slot: 1,
+            key: decoder.domain_key,
+            account: None,
+        };
+        let value = decoder.decode(&rpc_client, update).await.unwrap();
+        assert_eq!(value, Some(json!({ "type": "domainDeleted" })));
+    }
+}
@@ -0,0 +1,295 @@
+use std::{
+    collections::{HashMap, HashSet},
+    str::FromStr,
+    sync::Mutex,
+};
+
+use crate::{
+    append_trace,
+    sns::{params::parse_params, tokenization::get_tokenized_domain},
+    trace, ErrorType,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sns_sdk::{derivation::ROOT_DOMAIN_ACCOUNT, non_blocking::resolve};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_config::RpcProgramAccountsConfig,
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
+
+use super::subscription::{account_config, token_accounts_target, Decode, Target, Update};
+
+// NameRecordHeader layout: parent_name (32) | owner (32) | class (32)
+const PARENT_OFFSET: usize = 0;
+const OWNER_OFFSET: usize = 32;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    owner: String,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["owner"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub struct Decoder {
+    owner: Pubkey,
+    /// Domains held directly by the owner
+    domains: Mutex<HashSet<Pubkey>>,
+    /// Domains tokenized as the mints of the owner's token accounts, `None` for other mints
+    mints: Mutex<HashMap<Pubkey, Option<Pubkey>>>,
+}
+
+// Domains transferred to the owner are seen by a program subscription filtered on the owner. That
+// filter does not match anymore once a domain is transferred away, so the domains held by the
+// owner are also watched individually. Tokenized domains are followed through the owner's token
+// accounts.
+pub async fn process(
+    rpc_client: &RpcClient,
+    params: Value,
+) -> Result<(Vec<Target>, Decoder), crate::Error> {
+    let params = Params::deserialize(params)?;
+    let owner =
+        Pubkey::from_str(&params.owner).map_err(|e| trace!(ErrorType::InvalidParameters, e))?;
+    let domains = resolve::get_domains_owner(rpc_client, owner)
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?;
+    let config = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                PARENT_OFFSET,
+                ROOT_DOMAIN_ACCOUNT.to_bytes().to_vec(),
+            )),
+            RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                OWNER_OFFSET,
+                owner.to_bytes().to_vec(),
+            )),
+        ]),
+        account_config: account_config(),
+        ..Default::default()
+    };
+    let mut targets = vec![
+        Target::Program(spl_name_service::id(), config),
+        token_accounts_target(Some(&owner), None),
+    ];
+    targets.extend(domains.iter().map(|k| Target::Account(*k)));
+    let decoder = Decoder {
+        owner,
+        domains: Mutex::new(domains.into_iter().collect()),
+        mints: Mutex::new(HashMap::new()),
+    };
+    Ok((targets, decoder))
+}
+
+impl Decoder {
+    fn held_domain(&self, update: &Update) -> Option<Pubkey> {
+        let account = update.account.as_ref()?;
+        if account.owner != spl_name_service::id() {
+            return None;
+        }
+        let header =
+            NameRecordHeader::unpack_unchecked(account.data.get(..NameRecordHeader::LEN)?).ok()?;
+        (header.owner == self.owner).then_some(update.key)
+    }
+
+    async fn decode_token_account(
+        &self,
+        rpc_client: &RpcClient,
+        data: &[u8],
+    ) -> Result<Option<Value>, crate::Error> {
+        let token_account =
+            spl_token::state::Account::unpack(data).map_err(|e| trace!(ErrorType::Generic, e))?;
+        if token_account.amount > 1 {
+            return Ok(None);
+        }
+        let cached = self.mints.lock().unwrap().get(&token_account.mint).copied();
+        let domain_key = match cached {
+            Some(domain_key) => domain_key,
+            None => {
+                let domain_key = get_tokenized_domain(rpc_client, &token_account.mint)
+                    .await
+                    .map_err(|e| append_trace!(e))?;
+                self.mints
+                    .lock()
+                    .unwrap()
+                    .insert(token_account.mint, domain_key);
+                domain_key
+            }
+        };
+        let Some(domain_key) = domain_key else {
+            return Ok(None);
+        };
+        if token_account.amount == 0 {
+            return Ok(Some(removed(&domain_key)));
+        }
+        domain(rpc_client, &domain_key, true).await.map(Some)
+    }
+}
+
+impl Decode for Decoder {
+    async fn decode(
+        &self,
+        rpc_client: &RpcClient,
+        update: Update,
+    ) -> Result<Option<Value>, crate::Error> {
+        let Some(account) = &update.account else {
+            if self.domains.lock().unwrap().remove(&update.key) {
+                let domain_key = update.key.to_string();
+                return Ok(Some(
+                    json!({ "type": "domainDeleted", "domainKey": domain_key }),
+                ));
+            }
+            return Ok(None);
+        };
+        if account.owner == spl_token::id() {
+            return self.decode_token_account(rpc_client, &account.data).await;
+        }
+        if self.held_domain(&update).is_some() {
+            self.domains.lock().unwrap().insert(update.key);
+            return domain(rpc_client, &update.key, false).await.map(Some);
+        }
+        // The domain was transferred away, or tokenized
+        if self.domains.lock().unwrap().remove(&update.key) {
+            return Ok(Some(removed(&update.key)));
+        }
+        Ok(None)
+    }
+
+    fn watch(&self, update: &Update) -> Vec<Pubkey> {
+        self.held_domain(update).into_iter().collect()
+    }
+}
+
+async fn domain(
+    rpc_client: &RpcClient,
+    domain_key: &Pubkey,
+    tokenized: bool,
+) -> Result<Value, crate::Error> {
+    let domain = resolve::resolve_reverse_batch(rpc_client, &[*domain_key])
+        .await
+        .map_err(|e| trace!(ErrorType::Generic, e))?
+        .pop()
+        .flatten();
+    Ok(json!({
+        "type": "domain",
+        "domain": domain,
+        "domainKey": domain_key.to_string(),
+        "tokenized": tokenized,
+    }))
+}
+
+fn removed(domain_key: &Pubkey) -> Value {
+    json!({ "type": "domainRemoved", "domainKey": domain_key.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::account::Account;
+    use spl_token::state::AccountState;
+
+    use super::*;
+
+    fn name_account(owner: &Pubkey) -> Account {
+        let header = NameRecordHeader {
+            parent_name: ROOT_DOMAIN_ACCOUNT,
+            owner: *owner,
+            class: Pubkey::default(),
+        };
+        let mut data = vec![0; NameRecordHeader::LEN];
+        header.pack_into_slice(&mut data);
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_name_service::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn token_account(owner: &Pubkey, mint: &Pubkey, amount: u64) -> Account {
+        let token_account = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(token_account, &mut data).unwrap();
+        Account {
+            lamports: 1,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decode() {
+        let owner = Pubkey::new_unique();
+        let domain_key = Pubkey::new_unique();
+        let tokenized_key = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let decoder = Decoder {
+            owner,
+            domains: Mutex::new(HashSet::from([domain_key])),
+            mints: Mutex::new(HashMap::from([(mint, Some(tokenized_key))])),
+        };
+        let rpc_client = RpcClient::new("http://localhost:8899".to_owned());
+        let update = |key, account| Update {
+            slot: 1,
+            key,
+            account,
+        };
+
+        let held = update(Pubkey::new_unique(), Some(name_account(&owner)));
+        assert_eq!(decoder.watch(&held), vec![held.key]);
+
+        // Transferred away
+        let transferred = update(domain_key, Some(name_account(&Pubkey::new_unique())));
+        assert!(decoder.watch(&transferred).is_empty());
+        let value = decoder.decode(&rpc_client, transferred).await.unwrap();
+        assert_eq!(value, Some(removed(&domain_key)));
+        let value = decoder
+            .decode(&rpc_client, update(domain_key, None))
+            .await
+            .unwrap();
+        assert_eq!(value, None);
+
+        // Deleted
+        decoder.domains.lock().unwrap().insert(domain_key);
+        let value = decoder
+            .decode(&rpc_client, update(domain_key, None))
+            .await
+            .unwrap();
+        assert_eq!(
+            value,
+            Some(json!({ "type": "domainDeleted", "domainKey": domain_key.to_string() }))
+        );
+
+        // NFT transferred away
+        let account = token_account(&owner, &mint, 0);
+        let value = decoder
+            .decode(&rpc_client, update(Pubkey::new_unique(), Some(account)))
+            .await
+            .unwrap();
+        assert_eq!(value, Some(removed(&tokenized_key)));
+
+        // Fungible tokens are ignored
+        let account = token_account(&owner, &mint, 10);
+        let value = decoder
+            .decode(&rpc_client, update(Pubkey::new_unique(), Some(account)))
+            .await
+            .unwrap();
+        assert_eq!(value, None);
+    }
+}
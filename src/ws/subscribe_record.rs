@@ -0,0 +1,196 @@
+use crate::{
+    append_trace,
+    sns::{
+        domain_name::DomainName,
+        find_domains_by_record::Version,
+        get_domain_data::{format_v1_data, parse_record_v2, Encoding, ValidationIdFormat},
+        get_record_key_from_domain_key,
+        params::parse_params,
+    },
+    trace, ErrorType,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sns_sdk::{
+    derivation::get_domain_key,
+    record::{Record, RecordVersion},
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{account::Account, program_pack::Pack, pubkey::Pubkey};
+use spl_name_service::state::NameRecordHeader;
+
+use super::subscription::{Decode, Target, Update};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Params {
+    domain: DomainName,
+    record: String,
+    version: Option<Version>,
+}
+
+impl Params {
+    const FIELDS: &'static [&'static str] = &["domain", "record", "version"];
+
+    pub fn deserialize(value: Value) -> Result<Self, crate::Error> {
+        parse_params(value, Self::FIELDS).map_err(|e| append_trace!(e))
+    }
+}
+
+pub struct Decoder {
+    domain_key: Pubkey,
+    record_key: Pubkey,
+    record: Record,
+    version: Version,
+}
+
+// The staleness and ROA of a V2 record depend on the domain owner, the domain account is watched
+// along with the record so that owner changes are reported
+pub fn process(params: Value) -> Result<(Vec<Target>, Decoder), crate::Error> {
+    let params = Params::deserialize(params)?;
+    let record =
+        Record::try_from_str(&params.record).map_err(|e| trace!(ErrorType::InvalidRecord, e))?;
+    let domain_key =
+        get_domain_key(&params.domain).map_err(|e| trace!(ErrorType::InvalidDomain, e))?;
+    let version = params.version.unwrap_or(Version::V2);
+    let (record_key, targets) = match version {
+        Version::V1 => {
+            let record_key = get_record_key_from_domain_key(&domain_key, record, RecordVersion::V1);
+            (record_key, vec![Target::Account(record_key)])
+        }
+        Version::V2 => {
+            let record_key = get_record_key_from_domain_key(&domain_key, record, RecordVersion::V2);
+            let targets = vec![Target::Account(record_key), Target::Account(domain_key)];
+            (record_key, targets)
+        }
+    };
+    let decoder = Decoder {
+        domain_key,
+        record_key,
+        record,
+        version,
+    };
+    Ok((targets, decoder))
+}
+
+async fn get_account(
+    rpc_client: &RpcClient,
+    key: &Pubkey,
+) -> Result<Option<Account>, crate::Error> {
+    let account = rpc_client
+        .get_account_with_commitment(key, rpc_client.commitment())
+        .await
+        .map_err(|e| trace!(ErrorType::SolanaRpcError, e))?
+        .value;
+    Ok(account)
+}
+
+impl Decoder {
+    /// `domain_account` is only read for V2 records
+    fn to_value(
+        &self,
+        account: Option<&Account>,
+        domain_account: Option<&Account>,
+    ) -> Result<Value, crate::Error> {
+        let Some(account) = account else {
+            return Ok(json!({ "type": "recordDeleted" }));
+        };
+        if account.data.len() < NameRecordHeader::LEN {
+            return Err(trace!(ErrorType::InvalidRecord));
+        }
+        let value = match self.version {
+            Version::V1 => {
+                let content = format_v1_data(
+                    &account.data,
+                    Some((self.record, self.record_key)),
+                    Encoding::Decoded,
+                )?;
+                Value::String(content)
+            }
+            Version::V2 => {
+                // The domain owner is needed to check the validation of the record
+                let domain_account = domain_account.ok_or(trace!(ErrorType::DomainNotFound))?;
+                let domain_header = domain_account
+                    .data
+                    .get(..NameRecordHeader::LEN)
+                    .ok_or(trace!(ErrorType::InvalidDomain))?;
+                let domain_header = NameRecordHeader::unpack_unchecked(domain_header)
+                    .map_err(|e| trace!(ErrorType::Generic, e))?;
+                let result = parse_record_v2(
+                    &domain_header.owner,
+                    &account.data,
+                    self.record,
+                    ValidationIdFormat::String,
+                )?;
+                serde_json::to_value(result).map_err(|e| trace!(ErrorType::Generic, e))?
+            }
+        };
+        Ok(json!({ "type": "record", "value": value }))
+    }
+}
+
+impl Decode for Decoder {
+    async fn decode(
+        &self,
+        rpc_client: &RpcClient,
+        update: Update,
+    ) -> Result<Option<Value>, crate::Error> {
+        // Unchanged records are not notified again, see `subscription::subscribe`
+        let (account, domain_account) = if update.key == self.domain_key {
+            let account = get_account(rpc_client, &self.record_key)
+                .await
+                .map_err(|e| append_trace!(e))?;
+            (account, update.account)
+        } else if self.version == Version::V2 && update.account.is_some() {
+            let domain_account = get_account(rpc_client, &self.domain_key)
+                .await
+                .map_err(|e| append_trace!(e))?;
+            (update.account, domain_account)
+        } else {
+            (update.account, None)
+        };
+        self.to_value(account.as_ref(), domain_account.as_ref())
+            .map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_targets() {
+        let (targets, decoder) = process(json!({ "domain": "bonfida", "record": "url" })).unwrap();
+        assert_eq!(targets.len(), 2);
+        assert!(matches!(targets[0], Target::Account(key) if key == decoder.record_key));
+        assert!(matches!(targets[1], Target::Account(key) if key == decoder.domain_key));
+
+        let params = json!({ "domain": "bonfida", "record": "url", "version": "v1" });
+        let (targets, decoder) = process(params).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert!(matches!(targets[0], Target::Account(key) if key == decoder.record_key));
+    }
+
+    #[test]
+    fn test_to_value() {
+        let (_, decoder) = process(json!({ "domain": "bonfida", "record": "url" })).unwrap();
+        let value = decoder.to_value(None, None).unwrap();
+        assert_eq!(value, json!({ "type": "recordDeleted" }));
+
+        let account = Account {
+            data: vec![0; NameRecordHeader::LEN],
+            ..Account::default()
+        };
+        let error = decoder.to_value(Some(&account), None).unwrap_err();
+        assert!(matches!(error.ty, ErrorType::DomainNotFound));
+        // A domain account shorter than its header
+        let domain_account = Account {
+            data: vec![0; 8],
+            ..Account::default()
+        };
+        let error = decoder
+            .to_value(Some(&account), Some(&domain_account))
+            .unwrap_err();
+        assert!(matches!(error.ty, ErrorType::InvalidDomain));
+    }
+}
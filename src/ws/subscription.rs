@@ -0,0 +1,381 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    future::{self, BoxFuture, Either},
+    stream::{BoxStream, SelectAll},
+    StreamExt,
+};
+use serde_json::{json, Value};
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::{
+    nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient},
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{
+    account::Account, commitment_config::CommitmentConfig, program_pack::Pack, pubkey::Pubkey,
+};
+
+use crate::{config::CONFIG, trace, ErrorType};
+
+lazy_static::lazy_static! {
+    static ref SUBSCRIPTION_LIMITER: SubscriptionLimiter =
+        SubscriptionLimiter::new(CONFIG.ws_max_subscriptions, CONFIG.ws_max_connections);
+}
+
+pub enum Target {
+    Account(Pubkey),
+    Program(Pubkey, RpcProgramAccountsConfig),
+}
+
+pub struct Update {
+    pub slot: u64,
+    pub key: Pubkey,
+    /// `None` when the account has been closed
+    pub account: Option<Account>,
+}
+
+impl Update {
+    fn new(slot: u64, key: Pubkey, account: UiAccount) -> Self {
+        let account = account
+            .decode::<Account>()
+            .filter(|a| a.lamports != 0 && !a.data.is_empty());
+        Self { slot, key, account }
+    }
+}
+
+pub trait Decode {
+    /// Returns `None` when the update does not change what the subscription reports
+    fn decode(
+        &self,
+        rpc_client: &RpcClient,
+        update: Update,
+    ) -> impl Future<Output = Result<Option<Value>, crate::Error>> + Send;
+
+    /// Accounts to watch from now on, e.g. domains received by a subscribed owner
+    fn watch(&self, _update: &Update) -> Vec<Pubkey> {
+        vec![]
+    }
+}
+
+pub fn account_config() -> RpcAccountInfoConfig {
+    RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        commitment: Some(CommitmentConfig::confirmed()),
+        ..Default::default()
+    }
+}
+
+/// Token accounts of `owner`, or of `mint` when `owner` is `None`
+pub fn token_accounts_target(owner: Option<&Pubkey>, mint: Option<&Pubkey>) -> Target {
+    // Token account layout: mint (32) | owner (32) | ...
+    let mut filters = vec![RpcFilterType::DataSize(
+        spl_token::state::Account::LEN as u64,
+    )];
+    if let Some(mint) = mint {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            0,
+            mint.to_bytes().to_vec(),
+        )));
+    }
+    if let Some(owner) = owner {
+        filters.push(RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+            32,
+            owner.to_bytes().to_vec(),
+        )));
+    }
+    let config = RpcProgramAccountsConfig {
+        filters: Some(filters),
+        account_config: account_config(),
+        ..Default::default()
+    };
+    Target::Program(spl_token::id(), config)
+}
+
+type UnsubscribeFn = Box<dyn FnOnce() -> BoxFuture<'static, ()> + Send>;
+
+async fn subscribe_target(
+    pubsub_client: &PubsubClient,
+    target: Target,
+) -> Result<(BoxStream<'_, Update>, UnsubscribeFn), crate::Error> {
+    let result = match target {
+        Target::Account(key) => pubsub_client
+            .account_subscribe(&key, Some(account_config()))
+            .await
+            .map(|(stream, unsubscribe)| {
+                let stream = stream
+                    .map(move |r| Update::new(r.context.slot, key, r.value))
+                    .boxed();
+                (stream, unsubscribe)
+            }),
+        Target::Program(program_id, config) => pubsub_client
+            .program_subscribe(&program_id, Some(config))
+            .await
+            .map(|(stream, unsubscribe)| {
+                let stream = stream
+                    .filter_map(|r| async move {
+                        let key = Pubkey::from_str(&r.value.pubkey).ok()?;
+                        Some(Update::new(r.context.slot, key, r.value.account))
+                    })
+                    .boxed();
+                (stream, unsubscribe)
+            }),
+    };
+    result.map_err(|e| trace!(ErrorType::SolanaRpcError, e))
+}
+
+/// Cancels the upstream subscriptions and releases their slots when dropped
+pub struct Subscription {
+    _cancel: oneshot::Sender<()>,
+    _slot: SubscriptionSlot,
+}
+
+/// Subscribes to every target, `slot` must hold one subscription per target. Accounts watched
+/// later on by the decoder take their slot from the limiter of `endpoint`.
+#[allow(clippy::too_many_arguments)]
+pub async fn subscribe<D: Decode + Send + Sync + 'static>(
+    pubsub_client: &Arc<PubsubClient>,
+    rpc_client: &Arc<RpcClient>,
+    endpoint: &str,
+    slot: SubscriptionSlot,
+    targets: Vec<Target>,
+    decoder: D,
+    id: u64,
+    mut notifications: mpsc::Sender<(u64, Value)>,
+) -> Result<Subscription, crate::Error> {
+    let (ready_sender, ready) = oneshot::channel();
+    let (cancel, mut cancelled) = oneshot::channel::<()>();
+    let pubsub_client = Arc::clone(pubsub_client);
+    let rpc_client = Arc::clone(rpc_client);
+    let endpoint = endpoint.to_owned();
+    tokio::spawn(async move {
+        let mut streams = SelectAll::new();
+        let mut unsubscribes = vec![];
+        let mut watched = HashSet::new();
+        let mut watch_slots = vec![];
+        let mut error = None;
+        for target in targets {
+            if let Target::Account(key) = &target {
+                watched.insert(*key);
+            }
+            match subscribe_target(&pubsub_client, target).await {
+                Ok((stream, unsubscribe)) => {
+                    streams.push(stream);
+                    unsubscribes.push(unsubscribe);
+                }
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        match error {
+            None => {
+                let _ = ready_sender.send(Ok(()));
+            }
+            Some(e) => {
+                let _ = ready_sender.send(Err(e));
+                drop(streams);
+                for unsubscribe in unsubscribes {
+                    unsubscribe().await;
+                }
+                return;
+            }
+        }
+        // Consecutive identical notifications about the same domain, e.g. lamports changes, are
+        // only sent once
+        let mut last = HashMap::new();
+        loop {
+            // The upstream subscriptions share one connection, so they end together
+            let update = match future::select(&mut cancelled, streams.next()).await {
+                Either::Left(_) => break,
+                Either::Right((None, _)) => {
+                    log::warn!("Upstream subscription {id} closed");
+                    let _ = notifications.try_send((id, json!({ "type": "closed" })));
+                    break;
+                }
+                Either::Right((Some(update), _)) => update,
+            };
+            for key in decoder.watch(&update) {
+                if watched.contains(&key) {
+                    continue;
+                }
+                let watch_slot = match get_subscription_limiter().acquire(&endpoint) {
+                    Ok(watch_slot) => watch_slot,
+                    Err(e) => {
+                        log::warn!("Subscription {id} cannot watch {key}: {e:?}");
+                        continue;
+                    }
+                };
+                match subscribe_target(&pubsub_client, Target::Account(key)).await {
+                    Ok((stream, unsubscribe)) => {
+                        watched.insert(key);
+                        watch_slots.push(watch_slot);
+                        streams.push(stream);
+                        unsubscribes.push(unsubscribe);
+                    }
+                    Err(e) => log::error!("Subscription {id} cannot watch {key}: {e:?}"),
+                }
+            }
+            let slot = update.slot;
+            let value = match decoder.decode(&rpc_client, update).await {
+                Ok(Some(value)) => value,
+                Ok(None) => continue,
+                Err(e) => {
+                    log::error!("Failed to decode notification for subscription {id}: {e:?}");
+                    continue;
+                }
+            };
+            let subject = value
+                .get("domainKey")
+                .and_then(Value::as_str)
+                .map(str::to_owned);
+            if last.get(&subject) == Some(&value) {
+                continue;
+            }
+            let mut notification = value.clone();
+            if let Value::Object(object) = &mut notification {
+                object.insert("slot".to_owned(), Value::from(slot));
+            }
+            // A client reading slower than the notifications arrive loses them rather than
+            // buffering without bound
+            match notifications.try_send((id, notification)) {
+                Ok(()) => {
+                    last.insert(subject, value);
+                }
+                Err(e) if e.is_full() => {
+                    log::warn!("Dropped notification for subscription {id}, the client is lagging");
+                }
+                Err(_) => break,
+            }
+        }
+        drop(streams);
+        for unsubscribe in unsubscribes {
+            unsubscribe().await;
+        }
+    });
+    ready.await.map_err(|e| trace!(ErrorType::Generic, e))??;
+    Ok(Subscription {
+        _cancel: cancel,
+        _slot: slot,
+    })
+}
+
+/// Counts active subscriptions and connections per endpoint, across connections
+pub struct SubscriptionLimiter {
+    counts: Arc<Mutex<HashMap<String, usize>>>,
+    limit: usize,
+    connection_counts: Arc<Mutex<HashMap<String, usize>>>,
+    connection_limit: usize,
+}
+
+/// Releases the counted subscriptions or connection when dropped
+pub struct SubscriptionSlot {
+    counts: Arc<Mutex<HashMap<String, usize>>>,
+    endpoint: String,
+    count: usize,
+}
+
+impl SubscriptionLimiter {
+    pub fn new(limit: usize, connection_limit: usize) -> Self {
+        Self {
+            counts: Arc::new(Mutex::new(HashMap::new())),
+            limit,
+            connection_counts: Arc::new(Mutex::new(HashMap::new())),
+            connection_limit,
+        }
+    }
+
+    pub fn acquire(&self, endpoint: &str) -> Result<SubscriptionSlot, crate::Error> {
+        self.acquire_many(endpoint, 1)
+    }
+
+    /// Acquires the slots of `count` upstream subscriptions at once
+    pub fn acquire_many(
+        &self,
+        endpoint: &str,
+        count: usize,
+    ) -> Result<SubscriptionSlot, crate::Error> {
+        acquire_in(&self.counts, self.limit, endpoint, count)
+            .ok_or(trace!(ErrorType::SubscriptionLimitReached, self.limit))
+    }
+
+    pub fn acquire_connection(&self, endpoint: &str) -> Result<SubscriptionSlot, crate::Error> {
+        acquire_in(&self.connection_counts, self.connection_limit, endpoint, 1).ok_or(trace!(
+            ErrorType::ConnectionLimitReached,
+            self.connection_limit
+        ))
+    }
+}
+
+fn acquire_in(
+    counts: &Arc<Mutex<HashMap<String, usize>>>,
+    limit: usize,
+    endpoint: &str,
+    count: usize,
+) -> Option<SubscriptionSlot> {
+    let mut guard = counts.lock().unwrap();
+    let current = guard.entry(endpoint.to_owned()).or_default();
+    if *current + count > limit {
+        if *current == 0 {
+            guard.remove(endpoint);
+        }
+        return None;
+    }
+    *current += count;
+    Some(SubscriptionSlot {
+        counts: Arc::clone(counts),
+        endpoint: endpoint.to_owned(),
+        count,
+    })
+}
+
+impl Drop for SubscriptionSlot {
+    fn drop(&mut self) {
+        let mut counts = self.counts.lock().unwrap();
+        if let Some(count) = counts.get_mut(&self.endpoint) {
+            *count -= self.count;
+            if *count == 0 {
+                counts.remove(&self.endpoint);
+            }
+        }
+    }
+}
+
+pub fn get_subscription_limiter() -> &'static SubscriptionLimiter {
+    &SUBSCRIPTION_LIMITER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_limiter() {
+        let limiter = SubscriptionLimiter::new(2, 1);
+        let a = limiter.acquire("endpoint").unwrap();
+        let b = limiter.acquire("endpoint").unwrap();
+        let e = limiter.acquire("endpoint").err().unwrap();
+        assert!(matches!(e.ty, ErrorType::SubscriptionLimitReached));
+        let _c = limiter.acquire("other").unwrap();
+        drop(a);
+        let d = limiter.acquire("endpoint").unwrap();
+        drop(d);
+        assert!(limiter.acquire_many("endpoint", 2).is_err());
+        drop(b);
+        let _e = limiter.acquire_many("endpoint", 2).unwrap();
+
+        let connection = limiter.acquire_connection("endpoint").unwrap();
+        let e = limiter.acquire_connection("endpoint").err().unwrap();
+        assert!(matches!(e.ty, ErrorType::ConnectionLimitReached));
+        assert!(limiter.acquire("endpoint").is_err());
+        drop(connection);
+        let _connection = limiter.acquire_connection("endpoint").unwrap();
+    }
+}